@@ -1,8 +1,108 @@
+use crate::response::ResponseMetrics;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use http::{request::Parts, HeaderMap, HeaderName, HeaderValue};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
 use std::sync::Arc;
 
+type ComponentResolver = Arc<dyn Fn(&str) -> String + Send + Sync>;
+type ResponseHook = Arc<dyn Fn(&ResponseMetrics) + Send + Sync>;
+type CsrfTokenSource = Arc<dyn Fn(&Parts) -> Option<String> + Send + Sync>;
+type FallibleLayout = Arc<dyn Fn(String) -> Result<String, LayoutError> + Send + Sync>;
+
+/// Error returned by a fallible layout function, see
+/// [InertiaConfig::new_fallible].
+pub type LayoutError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The version of the Inertia protocol the client speaks.
+///
+/// Inertia v2 added page object fields (like `encryptHistory`) that a
+/// v1 client doesn't know about. See
+/// [InertiaConfig::protocol_version].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProtocolVersion {
+    /// Inertia v1: only the original page object fields are sent.
+    #[default]
+    V1,
+    /// Inertia v2.
+    V2,
+}
+
+/// How the page object is written into the `data-page` attribute of
+/// initial page loads, see [InertiaConfig::data_page_encoding].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DataPageEncoding {
+    /// Plain json, which is what the Inertia client expects.
+    #[default]
+    Json,
+    /// Base64 encoded json.
+    ///
+    /// Avoids escaping every quote as `&quot;`, but the client has to
+    /// decode the attribute itself before Inertia reads it, e.g. with
+    /// `el.dataset.page = atob(el.dataset.page)` before `createInertiaApp`
+    /// (use `TextDecoder` on the decoded bytes for non-ascii props).
+    Base64,
+}
+
+/// What to do when a page's props exceed
+/// [InertiaConfig::max_props_bytes].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PropsLimit {
+    /// Log a warning and render the page anyway. The response's
+    /// [metrics](crate::ResponseMetrics::props_too_large) flag it.
+    Warn,
+    /// Respond with a `500 Internal Server Error` instead.
+    Error,
+}
+
+/// How trailing slashes on the page object `url` are treated, see
+/// [InertiaConfig::trailing_slash].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Keep the request path as is.
+    #[default]
+    Preserve,
+    /// Remove trailing slashes, e.g. `/users/` becomes `/users`.
+    Strip,
+    /// Add a trailing slash, e.g. `/users` becomes `/users/`.
+    Enforce,
+}
+
+#[derive(Clone)]
 struct Inner {
     version: Option<String>,
-    layout: Box<dyn Fn(String) -> String + Send + Sync>,
+    layout: Arc<dyn Fn(String) -> String + Send + Sync>,
+    try_layout: FallibleLayout,
+    error_overlay: bool,
+    encrypt_history: bool,
+    html_headers: HeaderMap,
+    component_resolver: Option<ComponentResolver>,
+    base_path: String,
+    stringify_unsafe_integers: bool,
+    protocol_version: ProtocolVersion,
+    request_header_name: HeaderName,
+    shared_props: Map<String, Value>,
+    title_prop: Option<String>,
+    lang_prop: Option<String>,
+    pretty_page_json: bool,
+    on_response: Option<ResponseHook>,
+    data_page_encoding: DataPageEncoding,
+    max_props_bytes: Option<(usize, PropsLimit)>,
+    clear_history_paths: Vec<String>,
+    audit_props: bool,
+    csrf_token: Option<CsrfTokenSource>,
+    deep_merge_shared: bool,
+    trailing_slash: TrailingSlash,
+    window_data: Map<String, Value>,
+    window_variable: String,
+    json_api: bool,
+    cache_tag_header: HeaderName,
+    cache_tag_separator: &'static str,
+    sort_props: bool,
+    layouts: HashMap<String, FallibleLayout>,
+    /// Layout names by component prefix, see
+    /// [InertiaConfig::layout_for].
+    component_layouts: Vec<(String, String)>,
 }
 
 #[derive(Clone)]
@@ -20,7 +120,74 @@ impl InertiaConfig {
         version: Option<String>,
         layout: Box<dyn Fn(String) -> String + Send + Sync>,
     ) -> InertiaConfig {
-        let inner = Inner { version, layout };
+        let layout: Arc<dyn Fn(String) -> String + Send + Sync> = Arc::from(layout);
+        let try_layout = {
+            let layout = layout.clone();
+            Arc::new(move |props| Ok(layout(props)))
+        };
+        InertiaConfig::from_layouts(version, layout, try_layout)
+    }
+
+    /// Constructs a new InertiaConfig object with a layout function
+    /// that can fail.
+    ///
+    /// When `layout` returns an error, initial page loads respond with
+    /// a `500 Internal Server Error` instead of a broken page.
+    pub fn new_fallible(
+        version: Option<String>,
+        layout: Box<dyn Fn(String) -> Result<String, LayoutError> + Send + Sync>,
+    ) -> InertiaConfig {
+        let try_layout: FallibleLayout = Arc::from(layout);
+        let layout = {
+            let try_layout = try_layout.clone();
+            Arc::new(move |props| {
+                try_layout(props).unwrap_or_else(|err| {
+                    eprintln!("Failed to render layout {err}");
+                    "".to_string()
+                })
+            })
+        };
+        InertiaConfig::from_layouts(version, layout, try_layout)
+    }
+
+    fn from_layouts(
+        version: Option<String>,
+        layout: Arc<dyn Fn(String) -> String + Send + Sync>,
+        try_layout: FallibleLayout,
+    ) -> InertiaConfig {
+        let inner = Inner {
+            version,
+            layout,
+            try_layout,
+            error_overlay: false,
+            encrypt_history: false,
+            html_headers: HeaderMap::new(),
+            component_resolver: None,
+            base_path: String::new(),
+            stringify_unsafe_integers: false,
+            protocol_version: ProtocolVersion::V1,
+            request_header_name: HeaderName::from_static("x-inertia"),
+            shared_props: Map::new(),
+            title_prop: None,
+            lang_prop: None,
+            pretty_page_json: false,
+            on_response: None,
+            data_page_encoding: DataPageEncoding::Json,
+            max_props_bytes: None,
+            clear_history_paths: Vec::new(),
+            audit_props: false,
+            csrf_token: None,
+            deep_merge_shared: false,
+            trailing_slash: TrailingSlash::Preserve,
+            window_data: Map::new(),
+            window_variable: "__APP_CONFIG__".to_string(),
+            json_api: false,
+            cache_tag_header: HeaderName::from_static("surrogate-key"),
+            cache_tag_separator: " ",
+            sort_props: false,
+            layouts: HashMap::new(),
+            component_layouts: Vec::new(),
+        };
         InertiaConfig {
             inner: Arc::new(inner),
         }
@@ -33,6 +200,619 @@ impl InertiaConfig {
 
     /// Returns a reference to the layout function.
     pub fn layout(&self) -> &(dyn Fn(String) -> String + Send + Sync) {
-        &self.inner.layout
+        &*self.inner.layout
+    }
+
+    /// Adds another layout under `name`, e.g. a separate html shell
+    /// for the marketing pages. Pages use it when their component
+    /// matches a [layout_for](Self::layout_for) rule or the handler
+    /// picks it with [Inertia::layout](crate::Inertia::layout); all
+    /// others use the default layout.
+    ///
+    /// The per-page options (like [title_from_prop](Self::title_from_prop))
+    /// apply to every layout.
+    pub fn add_layout<N: Into<String>>(
+        mut self,
+        name: N,
+        layout: Box<dyn Fn(String) -> String + Send + Sync>,
+    ) -> InertiaConfig {
+        let layout: FallibleLayout = Arc::new(move |props| Ok(layout(props)));
+        Arc::make_mut(&mut self.inner)
+            .layouts
+            .insert(name.into(), layout);
+        self
+    }
+
+    /// Renders the components starting with `component_prefix`, like
+    /// `Marketing/`, with the [added](Self::add_layout) layout `name`.
+    /// The first matching rule wins.
+    pub fn layout_for<P: Into<String>, N: Into<String>>(
+        mut self,
+        component_prefix: P,
+        name: N,
+    ) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner)
+            .component_layouts
+            .push((component_prefix.into(), name.into()));
+        self
+    }
+
+    /// The name of the layout for `component`, if a rule matches.
+    pub(crate) fn layout_name_for(&self, component: &str) -> Option<&str> {
+        self.inner
+            .component_layouts
+            .iter()
+            .find(|(prefix, _)| component.starts_with(prefix.as_str()))
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// Renders the page with the default layout, or the one added
+    /// under `layout`. An unknown name is an error. `window_data` is
+    /// merged over the config's [window data](Self::window_data).
+    pub(crate) fn try_layout(
+        &self,
+        props: String,
+        layout: Option<&str>,
+        window_data: &Map<String, Value>,
+    ) -> Result<String, LayoutError> {
+        let try_layout = match layout {
+            None => &self.inner.try_layout,
+            Some(name) => self
+                .inner
+                .layouts
+                .get(name)
+                .ok_or_else(|| format!("no layout named {name}"))?,
+        };
+        let page: Option<Value> =
+            if self.inner.title_prop.is_some() || self.inner.lang_prop.is_some() {
+                serde_json::from_str(&props).ok()
+            } else {
+                None
+            };
+        let prop = |path: &Option<String>| string_prop(page.as_ref()?, path.as_deref()?);
+        let title = prop(&self.inner.title_prop);
+        let lang = prop(&self.inner.lang_prop);
+        let props = match self.inner.data_page_encoding {
+            DataPageEncoding::Json => props,
+            DataPageEncoding::Base64 => BASE64_STANDARD.encode(props),
+        };
+        let mut html = try_layout(props)?;
+        if let Some(title) = title {
+            html = with_title(html, &title);
+        }
+        if let Some(lang) = lang {
+            html = with_lang(html, &lang);
+        }
+        if !self.inner.window_data.is_empty() || !window_data.is_empty() {
+            html = insert_before_scripts(html, &self.window_data_script(window_data));
+        }
+        Ok(html)
+    }
+
+    /// Shows render errors in the browser instead of a bare `500`.
+    ///
+    /// Only for development: the overlay includes error details.
+    pub(crate) fn error_overlay(mut self) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).error_overlay = true;
+        self
+    }
+
+    pub(crate) fn shows_error_overlay(&self) -> bool {
+        self.inner.error_overlay
+    }
+
+    /// Sets whether pages should have their history state encrypted
+    /// by default.
+    ///
+    /// Handlers can override this for a single response with
+    /// [Inertia::encrypt_history](crate::Inertia::encrypt_history).
+    /// Requires [ProtocolVersion::V2].
+    pub fn encrypt_history(mut self, encrypt: bool) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).encrypt_history = encrypt;
+        self
+    }
+
+    pub(crate) fn encrypts_history(&self) -> bool {
+        self.inner.encrypt_history
+    }
+
+    /// Adds a header to every initial page load (html) response.
+    ///
+    /// Inertia visits (json responses) don't include these headers.
+    pub fn html_header(mut self, name: HeaderName, value: HeaderValue) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner)
+            .html_headers
+            .append(name, value);
+        self
+    }
+
+    pub(crate) fn html_headers(&self) -> &HeaderMap {
+        &self.inner.html_headers
+    }
+
+    /// Sets a function mapping the component name passed to
+    /// [Inertia::render](crate::Inertia::render) to the name sent to
+    /// the client.
+    ///
+    /// Useful when handlers use logical names that differ from the
+    /// keys in the client's component registry. By default the name is
+    /// used as-is.
+    pub fn component_resolver<F>(mut self, resolver: F) -> InertiaConfig
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.inner).component_resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    pub(crate) fn resolve_component(&self, component: &str) -> String {
+        match &self.inner.component_resolver {
+            Some(resolver) => resolver(component),
+            None => component.to_string(),
+        }
+    }
+
+    /// Sets the path prefix the app is mounted under, e.g. `/app`
+    /// when a reverse proxy strips that prefix before forwarding
+    /// requests.
+    ///
+    /// The prefix is prepended to the `url` of every page object (and
+    /// the `X-Inertia-Location` of version conflicts). To prefix
+    /// generated asset urls as well, see
+    /// [Production::base_path](crate::vite::Production::base_path).
+    pub fn base_path<P: AsRef<str>>(mut self, base_path: P) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).base_path = normalize_base_path(base_path.as_ref());
+        self
+    }
+
+    /// Returns `path` with the configured base path prepended.
+    pub(crate) fn url_for(&self, path: &str) -> String {
+        format!("{}{}", self.inner.base_path, path)
+    }
+
+    /// Normalizes trailing slashes on the page object `url`, to match
+    /// the client router's routes. Defaults to
+    /// [TrailingSlash::Preserve].
+    ///
+    /// Only the path is changed; the query string is kept and the root
+    /// path stays `/`.
+    pub fn trailing_slash(mut self, trailing_slash: TrailingSlash) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).trailing_slash = trailing_slash;
+        self
+    }
+
+    /// Returns the page object `url` for a request to `url`.
+    pub(crate) fn page_url(&self, url: &str) -> String {
+        let (path, query) = match url.find('?') {
+            Some(index) => url.split_at(index),
+            None => (url, ""),
+        };
+        let path = match self.inner.trailing_slash {
+            TrailingSlash::Preserve => path.to_string(),
+            TrailingSlash::Strip => match path.trim_end_matches('/') {
+                "" => "/".to_string(),
+                path => path.to_string(),
+            },
+            TrailingSlash::Enforce if path.ends_with('/') => path.to_string(),
+            TrailingSlash::Enforce => format!("{}/", path),
+        };
+        self.url_for(&format!("{}{}", path, query))
+    }
+
+    /// Sorts the keys of the props (and of every object in them)
+    /// alphabetically, so responses can be diffed, e.g. together with
+    /// [pretty_page_json](Self::pretty_page_json).
+    ///
+    /// serde_json already sorts keys unless its `preserve_order`
+    /// feature is enabled somewhere in the dependency graph (e.g. by
+    /// this crate's `preserve_order` feature), so this only has an
+    /// effect then.
+    pub fn sort_props(mut self, enabled: bool) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).sort_props = enabled;
+        self
+    }
+
+    pub(crate) fn sorts_props(&self) -> bool {
+        self.inner.sort_props
+    }
+
+    /// Sends integer props that javascript can't represent exactly
+    /// (beyond ±2^53 - 1) as strings.
+    ///
+    /// This is a safety net: large ids would otherwise silently lose
+    /// precision in the browser. Prefer serializing such fields as
+    /// strings explicitly (e.g. with `#[serde(with = ...)]`) so the
+    /// client always sees the same type.
+    pub fn stringify_unsafe_integers(mut self, enabled: bool) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).stringify_unsafe_integers = enabled;
+        self
+    }
+
+    pub(crate) fn stringifies_unsafe_integers(&self) -> bool {
+        self.inner.stringify_unsafe_integers
+    }
+
+    /// Sets the Inertia protocol version of the client, defaulting to
+    /// [ProtocolVersion::V1].
+    ///
+    /// v2-only fields are never sent to a v1 client. Rendering a page
+    /// that uses a v2 feature (like history encryption) in v1 mode
+    /// fails with a `500 Internal Server Error` rather than silently
+    /// sending fields the client ignores.
+    pub fn protocol_version(mut self, version: ProtocolVersion) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).protocol_version = version;
+        self
+    }
+
+    pub(crate) fn protocol(&self) -> ProtocolVersion {
+        self.inner.protocol_version
+    }
+
+    /// Clears the client's history state on every page rendered at
+    /// `path`, e.g. `/login`.
+    ///
+    /// Redirecting there after a logout then clears the history
+    /// without each handler having to call
+    /// [Inertia::clear_history](crate::Inertia::clear_history). The
+    /// query string is ignored when matching. Requires
+    /// [ProtocolVersion::V2]; under [ProtocolVersion::V1] the paths are
+    /// ignored with a warning.
+    pub fn clear_history_on<P: Into<String>>(mut self, path: P) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner)
+            .clear_history_paths
+            .push(path.into());
+        self
+    }
+
+    pub(crate) fn clears_history_at(&self, url: &str) -> bool {
+        let path = url.split_once('?').map_or(url, |(path, _)| path);
+        self.inner.clear_history_paths.iter().any(|p| p == path)
+    }
+
+    /// Sets the header marking Inertia visits, `X-Inertia` by default.
+    ///
+    /// The header is used both to detect Inertia requests and to mark
+    /// Inertia responses (and is listed in `Vary`). Only useful when a
+    /// proxy between the client and the server renames or strips the
+    /// standard header.
+    pub fn request_header_name(mut self, name: HeaderName) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).request_header_name = name;
+        self
+    }
+
+    pub(crate) fn inertia_header(&self) -> &HeaderName {
+        &self.inner.request_header_name
+    }
+
+    /// Sets the header [cache tags](crate::Inertia::cache_tag) are
+    /// sent in and the separator they are joined by. The default is
+    /// `Surrogate-Key` with spaces (Fastly); Cloudflare, for example,
+    /// reads a comma separated `Cache-Tag`:
+    ///
+    /// ```rust
+    /// # use axum_inertia::InertiaConfig;
+    /// # use http::HeaderName;
+    /// # let config = InertiaConfig::new(None, Box::new(|props| props));
+    /// let config = config.cache_tag_header(HeaderName::from_static("cache-tag"), ",");
+    /// ```
+    pub fn cache_tag_header(mut self, name: HeaderName, separator: &'static str) -> InertiaConfig {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.cache_tag_header = name;
+        inner.cache_tag_separator = separator;
+        self
+    }
+
+    pub(crate) fn cache_tag_header_name(&self) -> &HeaderName {
+        &self.inner.cache_tag_header
+    }
+
+    pub(crate) fn cache_tag_separator(&self) -> &'static str {
+        self.inner.cache_tag_separator
+    }
+
+    /// Shares a prop with every page, e.g. the app name.
+    ///
+    /// Props passed to [Inertia::render](crate::Inertia::render)
+    /// override shared props with the same key.
+    pub fn share<K: Into<String>>(mut self, key: K, value: Value) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner)
+            .shared_props
+            .insert(key.into(), value);
+        self
+    }
+
+    pub(crate) fn shared_props(&self) -> &Map<String, Value> {
+        &self.inner.shared_props
+    }
+
+    /// Defines a value on a global object in initial page loads, e.g.
+    /// feature flags or an error tracking DSN that must be available
+    /// before the app mounts.
+    ///
+    /// The values are written as `window.__APP_CONFIG__ = {...}` in an
+    /// inline script ahead of the layout's scripts (see
+    /// [window_variable](Self::window_variable)). Unlike props, they
+    /// aren't part of Inertia visits. Handlers can add values of their
+    /// own with [Inertia::window_data](crate::Inertia::window_data). A
+    /// [Content Security
+    /// Policy](crate::vite::Production::csp) must allow the inline
+    /// script.
+    pub fn window_data<K: Into<String>>(mut self, key: K, value: Value) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner)
+            .window_data
+            .insert(key.into(), value);
+        self
+    }
+
+    /// Sets the name of the [window_data](Self::window_data) global,
+    /// defaulting to `__APP_CONFIG__`.
+    pub fn window_variable<N: Into<String>>(mut self, name: N) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).window_variable = name.into();
+        self
+    }
+
+    /// The inline script defining the window data, with the values of
+    /// `overrides` replacing the config's.
+    fn window_data_script(&self, overrides: &Map<String, Value>) -> String {
+        let script = |data| {
+            format!(
+                "<script>window[{}] = {};</script>",
+                script_json(&self.inner.window_variable),
+                script_json(data)
+            )
+        };
+        if overrides.is_empty() {
+            return script(&self.inner.window_data);
+        }
+        let mut data = self.inner.window_data.clone();
+        data.extend(overrides.clone());
+        script(&data)
+    }
+
+    /// Responds to requests that accept `application/json` with just
+    /// the props, so Inertia routes can double as a lightweight api
+    /// for other clients (mobile apps, tests).
+    ///
+    /// `X-Inertia` takes precedence: Inertia visits always get the
+    /// page object, whatever their `Accept` header. Other requests get
+    /// the props as json if `Accept` ranks `application/json` above
+    /// `text/html` (taking q-values into account), and the html page
+    /// otherwise, so browsers sending `*/*` still get html. Responses
+    /// then vary on `Accept`.
+    pub fn json_api(mut self, enabled: bool) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).json_api = enabled;
+        self
+    }
+
+    pub(crate) fn serves_json_api(&self) -> bool {
+        self.inner.json_api
+    }
+
+    /// Merges objects that are both shared and passed to
+    /// [Inertia::render](crate::Inertia::render) recursively, so a
+    /// shared `auth.user` and a page's `auth.permissions` both survive.
+    /// Page values still win on conflicts.
+    ///
+    /// Off by default: a page's prop replaces the shared prop with the
+    /// same key wholesale.
+    pub fn deep_merge_shared(mut self, enabled: bool) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).deep_merge_shared = enabled;
+        self
+    }
+
+    pub(crate) fn deep_merges_shared(&self) -> bool {
+        self.inner.deep_merge_shared
+    }
+
+    /// Pretty prints the page object embedded in initial page loads,
+    /// which makes `data-page` easier to read in devtools.
+    ///
+    /// On by default for [vite::Development](crate::vite::Development)
+    /// configs, otherwise the page object is compact. Inertia visits
+    /// always get compact json.
+    pub fn pretty_page_json(mut self, pretty: bool) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).pretty_page_json = pretty;
+        self
+    }
+
+    pub(crate) fn pretty_prints_page(&self) -> bool {
+        self.inner.pretty_page_json
+    }
+
+    /// Sets how the page object is encoded in `data-page`, plain json
+    /// by default.
+    pub fn data_page_encoding(mut self, encoding: DataPageEncoding) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).data_page_encoding = encoding;
+        self
+    }
+
+    /// Guards against accidentally shipping huge props, like a whole
+    /// table, by checking the size of the serialized props of each
+    /// page against `limit` bytes.
+    ///
+    /// Pages over the limit are logged or fail to render, depending on
+    /// `action`. This costs an extra serialization of the props.
+    pub fn max_props_bytes(mut self, limit: usize, action: PropsLimit) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).max_props_bytes = Some((limit, action));
+        self
+    }
+
+    pub(crate) fn props_limit(&self) -> Option<(usize, PropsLimit)> {
+        self.inner.max_props_bytes
+    }
+
+    /// Calls `hook` after rendering each Inertia response, e.g. to
+    /// count responses by component with a metrics library.
+    pub fn on_response<F>(mut self, hook: F) -> InertiaConfig
+    where
+        F: Fn(&ResponseMetrics) + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.inner).on_response = Some(Arc::new(hook));
+        self
+    }
+
+    /// Includes the final serialized props of each page (after partial
+    /// reload filtering and merging) in the [ResponseMetrics] passed to
+    /// [on_response](Self::on_response), e.g. to audit what data left
+    /// the server.
+    ///
+    /// Off by default: the props may well contain sensitive data, so
+    /// take care where they are recorded.
+    pub fn audit_props(mut self, enabled: bool) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).audit_props = enabled;
+        self
+    }
+
+    pub(crate) fn audits_props(&self) -> bool {
+        self.inner.audit_props
+    }
+
+    pub(crate) fn response_hook(&self) -> Option<&(dyn Fn(&ResponseMetrics) + Send + Sync)> {
+        self.inner.on_response.as_deref()
+    }
+
+    /// Exposes a CSRF token to the client, both as a `csrf_token`
+    /// shared prop and as a `<meta name="csrf-token">` tag in the head
+    /// of initial page loads.
+    ///
+    /// The crate has no CSRF protection of its own: `token` gets the
+    /// token for a request, typically from an extension set by the
+    /// app's CSRF middleware.
+    ///
+    /// ```rust
+    /// # use axum_inertia::InertiaConfig;
+    /// #[derive(Clone)]
+    /// struct CsrfToken(String);
+    ///
+    /// # let config = InertiaConfig::new(None, Box::new(|props| props));
+    /// let config = config.csrf_token(|parts| {
+    ///     parts.extensions.get::<CsrfToken>().map(|token| token.0.clone())
+    /// });
+    /// ```
+    pub fn csrf_token<F>(mut self, token: F) -> InertiaConfig
+    where
+        F: Fn(&Parts) -> Option<String> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.inner).csrf_token = Some(Arc::new(token));
+        self
+    }
+
+    pub(crate) fn csrf_token_for(&self, parts: &Parts) -> Option<String> {
+        (self.inner.csrf_token.as_ref()?)(parts)
+    }
+
+    /// Takes the `<title>` of initial page loads from the prop at the
+    /// dotted `path`, like `meta.title`.
+    ///
+    /// The title rendered by the layout is replaced (or added before
+    /// `</head>` when there is none). Pages without a string at `path`
+    /// keep the layout's title.
+    pub fn title_from_prop<P: Into<String>>(mut self, path: P) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).title_prop = Some(path.into());
+        self
+    }
+
+    /// Takes the `<html lang>` of initial page loads from the prop at
+    /// the dotted `path`, like `locale`, for apps that pick the locale
+    /// per request.
+    ///
+    /// This overrides the `lang` rendered by the layout, whether it
+    /// comes from [vite](crate::vite) or a template. Pages without a
+    /// string at `path` keep the layout's `lang`.
+    pub fn lang_from_prop<P: Into<String>>(mut self, path: P) -> InertiaConfig {
+        Arc::make_mut(&mut self.inner).lang_prop = Some(path.into());
+        self
+    }
+}
+
+/// Looks up the string prop at the dotted `path` in the page object.
+fn string_prop(page: &Value, path: &str) -> Option<String> {
+    let pointer: String = std::iter::once("props")
+        .chain(path.split('.'))
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect();
+    page.pointer(&pointer)?.as_str().map(str::to_string)
+}
+
+/// Replaces the contents of the first `<title>` in `html` with the
+/// escaped `title`.
+fn with_title(mut html: String, title: &str) -> String {
+    let title = maud::html! { (title) }.into_string();
+    if let Some(start) = html.find("<title>").map(|start| start + "<title>".len()) {
+        if let Some(end) = html[start..].find("</title>") {
+            html.replace_range(start..start + end, &title);
+            return html;
+        }
+    }
+    insert_into_head(html, &format!("<title>{}</title>", title))
+}
+
+/// Inserts `tag` at the end of the head of `html`, if it has one.
+pub(crate) fn insert_into_head(mut html: String, tag: &str) -> String {
+    if let Some(head) = html.find("</head>") {
+        html.insert_str(head, tag);
+    }
+    html
+}
+
+/// Serializes `value` for an inline script, escaping `<`, `>` and `&`
+/// so strings can't close the script tag.
+fn script_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value)
+        .unwrap_or_default()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
+}
+
+/// Inserts `tag` before the first script in the head of `html`, or at
+/// the end of the head if it has no scripts.
+fn insert_before_scripts(mut html: String, tag: &str) -> String {
+    let Some(head) = html.find("</head>") else {
+        return html;
+    };
+    let index = html[..head].find("<script").unwrap_or(head);
+    html.insert_str(index, tag);
+    html
+}
+
+/// Sets the `lang` attribute of the `<html>` tag in `html`.
+fn with_lang(mut html: String, lang: &str) -> String {
+    let lang = maud::html! { (lang) }.into_string();
+    let Some(start) = html
+        .match_indices("<html")
+        .map(|(start, _)| start + "<html".len())
+        .find(|&start| html[start..].starts_with([' ', '>', '\n', '\t']))
+    else {
+        return html;
+    };
+    let Some(end) = html[start..].find('>').map(|end| start + end) else {
+        return html;
+    };
+    let attribute = ['"', '\''].into_iter().find_map(|quote| {
+        let value = html[start..end].find(&format!(" lang={quote}"))? + " lang=\"".len();
+        Some((start + value, quote))
+    });
+    match attribute {
+        Some((value, quote)) => {
+            if let Some(value_end) = html[value..end].find(quote) {
+                html.replace_range(value..value + value_end, &lang);
+            }
+        }
+        None => html.insert_str(start, &format!(" lang=\"{}\"", lang)),
+    }
+    html
+}
+
+/// Normalizes a base path to have a leading slash and no trailing
+/// slash, with `/` and the empty string meaning no prefix at all.
+pub(crate) fn normalize_base_path(base_path: &str) -> String {
+    let base_path = base_path.trim_matches('/');
+    if base_path.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", base_path)
     }
 }
@@ -0,0 +1,61 @@
+use crate::Inertia;
+use axum::response::IntoResponse;
+use http::StatusCode;
+use serde_json::json;
+
+/// An error that renders as an Inertia error page.
+///
+/// [IntoResponse] has no access to the request, so the error holds on
+/// to the [Inertia] extractor along with the component to render.
+/// Create one with [Inertia::error] and return it from a handler:
+///
+/// ```rust
+/// use axum_inertia::{Inertia, InertiaError};
+/// use axum::response::{IntoResponse, Response};
+/// use http::StatusCode;
+/// use serde_json::json;
+///
+/// # fn find_post() -> Option<&'static str> { None }
+/// async fn show_post(i: Inertia) -> Result<Response, InertiaError> {
+///     let post = find_post().ok_or_else(|| i.error("Error", StatusCode::NOT_FOUND, "Post not found"))?;
+///     Ok(i.render("Posts/Show", json!({ "post": post })).into_response())
+/// }
+/// ```
+///
+/// The component gets `status` and `message` props, and the response
+/// has `status` as its status code.
+#[derive(Clone)]
+pub struct InertiaError {
+    inertia: Inertia,
+    component: &'static str,
+    status: StatusCode,
+    message: String,
+}
+
+impl InertiaError {
+    pub fn new<M: Into<String>>(
+        inertia: Inertia,
+        component: &'static str,
+        status: StatusCode,
+        message: M,
+    ) -> InertiaError {
+        InertiaError {
+            inertia,
+            component,
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for InertiaError {
+    fn into_response(self) -> axum::response::Response {
+        let props = json!({ "status": self.status.as_u16(), "message": self.message });
+        let mut response = self.inertia.render(self.component, props).into_response();
+        // Keep failures to render the error page itself visible.
+        if response.status().is_success() {
+            *response.status_mut() = self.status;
+        }
+        response
+    }
+}
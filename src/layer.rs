@@ -0,0 +1,109 @@
+//! Providing [InertiaConfig] with a tower [Layer] instead of router
+//! state.
+//!
+//! Routers that don't carry an [InertiaConfig] in their state can
+//! wrap their routes in an [InertiaLayer], which inserts the config
+//! into the extensions of every request:
+//!
+//! ```rust
+//! use axum_inertia::{vite, Inertia, InertiaLayer};
+//! use axum::{Router, routing::get, response::IntoResponse};
+//! use serde_json::json;
+//!
+//! async fn get_root(i: Inertia) -> impl IntoResponse {
+//!     i.render("Pages/Home", json!({}))
+//! }
+//!
+//! let inertia = vite::Development::default()
+//!     .port(5173)
+//!     .main("src/main.ts")
+//!     .into_config();
+//! let app: Router = Router::new()
+//!     .route("/", get(get_root))
+//!     .layer(InertiaLayer::new(inertia));
+//! ```
+use crate::InertiaConfig;
+use axum::extract::FromRef;
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// A [Layer] that makes an [InertiaConfig] available to the
+/// [Inertia](crate::Inertia) extractor through request extensions.
+///
+/// The config is reference counted, so cloning it into each request
+/// is cheap.
+#[derive(Clone)]
+pub struct InertiaLayer {
+    config: InertiaConfig,
+}
+
+impl InertiaLayer {
+    pub fn new(config: InertiaConfig) -> InertiaLayer {
+        InertiaLayer { config }
+    }
+}
+
+impl<S> Layer<S> for InertiaLayer {
+    type Service = InertiaService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        InertiaService {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// The middleware service created by [InertiaLayer].
+#[derive(Clone)]
+pub struct InertiaService<S> {
+    inner: S,
+    config: InertiaConfig,
+}
+
+impl<S, B> Service<http::Request<B>> for InertiaService<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        request.extensions_mut().insert(self.config.clone());
+        self.inner.call(request)
+    }
+}
+
+/// Router states the [Inertia](crate::Inertia) extractor can be used
+/// with.
+///
+/// This is implemented for any state that [InertiaConfig] can be
+/// extracted from (see [FromRef]), and for `()`, in which case the
+/// config must be provided by an [InertiaLayer]. A missing layer is
+/// only noticed when a request is extracted, which then logs an error
+/// and responds with `500 Internal Server Error`.
+pub trait InertiaState {
+    /// Returns the config held by this state, if any.
+    fn inertia_config(&self) -> Option<InertiaConfig>;
+}
+
+impl<S> InertiaState for S
+where
+    InertiaConfig: FromRef<S>,
+{
+    fn inertia_config(&self) -> Option<InertiaConfig> {
+        Some(InertiaConfig::from_ref(self))
+    }
+}
+
+impl InertiaState for () {
+    fn inertia_config(&self) -> Option<InertiaConfig> {
+        None
+    }
+}
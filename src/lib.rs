@@ -17,19 +17,19 @@
 //! This does the following:
 //!
 //! - If the incoming request is the initial page load (i.e., does not
-//! have the `X-Inertia` header set to `true`), the
-//! [render](Inertia::render) method responds with an html page, which
-//! is configurable when setting up the initial Inertia state (see
-//! [Getting started](#getting-started) below).
+//!   have the `X-Inertia` header set to `true`), the
+//!   [render](Inertia::render) method responds with an html page, which
+//!   is configurable when setting up the initial Inertia state (see
+//!   [Getting started](#getting-started) below).
 //!
 //! - Otherwise, the handler responses with the standard inertia
-//! "Page" object json, with the included component and page props
-//! passed to [render](Inertia::render).
+//!   "Page" object json, with the included component and page props
+//!   passed to [render](Inertia::render).
 //!
 //! - If the request has a mismatching asset version (again, this is
-//! configurable), the handler responds with a `409 Conflict` to tell
-//! the client to reload the page. The function body of the handler is
-//! not executed in this case.
+//!   configurable), the handler responds with a `409 Conflict` to tell
+//!   the client to reload the page. The function body of the handler is
+//!   not executed in this case.
 //!
 //! # Getting started
 //!
@@ -79,9 +79,13 @@
 //! the name of the component to render, and the page props
 //! (serializable to json).
 //!
-//! Using the extractor in a handler *requires* that you use
-//! [axum::Router::with_state] to initialize Inertia in your
-//! routes. In fact, it won't compile if you don't!
+//! Using the extractor in a handler *requires* that you provide an
+//! [InertiaConfig], either as router state with
+//! [axum::Router::with_state] or with an [InertiaLayer]. For routers
+//! with state this is checked at compile time. Stateless routers can
+//! only be checked per request: without an [InertiaLayer] the
+//! extractor logs an error and responds with `500 Internal Server
+//! Error`.
 //!
 //! # Using InertiaConfig as substate
 //!
@@ -121,6 +125,11 @@
 //! # async fn get_root(_i: Inertia) -> impl IntoResponse { "foo" }
 //! ```
 //!
+//! # Using a layer instead of state
+//!
+//! Routers without state can provide the config with an
+//! [InertiaLayer] instead, see the [layer] module.
+//!
 //! # Configuring development and production
 //!
 //! See the [vite] module for more information.
@@ -133,42 +142,81 @@
 //! [Extractor]: https://docs.rs/axum/latest/axum/#extractors
 
 use async_trait::async_trait;
-use axum::extract::{FromRef, FromRequestParts};
+use axum::extract::FromRequestParts;
+use axum::response::IntoResponse;
 pub use config::InertiaConfig;
-use http::{request::Parts, HeaderMap, HeaderValue, StatusCode};
-use page::Page;
-use props::Props;
+use config::{PropsLimit, ProtocolVersion};
+pub use error::InertiaError;
+use http::{
+    header::{LOCATION, SET_COOKIE},
+    request::Parts,
+    HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+};
+pub use layer::{InertiaLayer, InertiaState};
+pub use page::Page;
+use props::{IntoInertiaProps, Props, PropsError};
 use request::Request;
-use response::Response;
+use response::RenderError;
+pub use response::{Response, ResponseContext, ResponseMetrics};
+use serde::Serialize;
+use serde_json::{Map, Value};
 
 pub mod config;
+mod error;
+pub mod layer;
 mod page;
 pub mod partial;
 pub mod props;
 mod request;
 mod response;
+pub mod testing;
 pub mod vite;
 
 #[derive(Clone)]
 pub struct Inertia {
     request: Request,
     config: InertiaConfig,
+    encrypt_history: Option<bool>,
+    clear_history: bool,
+    headers: HeaderMap,
+    prefetch: Vec<String>,
+    force_reload: bool,
+    without_shared: bool,
+    /// Merge props by (dotted) path; serialization errors are kept
+    /// until rendering.
+    merge_props: Vec<(String, Result<Value, String>)>,
+    /// Props only sent on the initial page load, see
+    /// [only_on_first_load](Inertia::only_on_first_load).
+    first_load_props: Vec<(String, Result<Value, String>)>,
+    cache_tags: Vec<String>,
+    layout: Option<String>,
+    window_data: Map<String, Value>,
 }
 
 #[async_trait]
 impl<S> FromRequestParts<S> for Inertia
 where
-    S: Send + Sync,
-    InertiaConfig: FromRef<S>,
+    S: InertiaState + Send + Sync,
 {
     type Rejection = (StatusCode, HeaderMap<HeaderValue>);
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let config = InertiaConfig::from_ref(state);
-        let request = Request::from_request_parts(parts, state).await?;
+        // A config from an InertiaLayer takes precedence over the
+        // router state.
+        let config = match parts.extensions.get::<InertiaConfig>() {
+            Some(config) => config.clone(),
+            None => state.inertia_config().ok_or_else(|| {
+                eprintln!("No InertiaConfig found, is the router wrapped in an InertiaLayer?");
+                (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new())
+            })?,
+        };
+        let mut request = Request::from_parts(parts, config.inertia_header()).await?;
+        request.csrf_token = config.csrf_token_for(parts);
 
         // Respond with a 409 conflict if X-Inertia-Version values
-        // don't match for GET requests. See more at:
+        // don't match for GET requests. This happens before the
+        // handler runs, so stale partial reloads get a full reload
+        // rather than props filtered for an outdated page. See more at:
         // https://inertiajs.com/the-protocol#asset-versioning
         if parts.method == "GET"
             && request.is_xhr
@@ -176,7 +224,10 @@ where
             && request.version != config.version()
         {
             let mut headers = HeaderMap::new();
-            headers.insert("X-Inertia-Location", parts.uri.path().parse().unwrap());
+            headers.insert(
+                "X-Inertia-Location",
+                config.url_for(&request.url).parse().unwrap(),
+            );
             return Err((StatusCode::CONFLICT, headers));
         }
 
@@ -186,27 +237,542 @@ where
 
 impl Inertia {
     fn new(request: Request, config: InertiaConfig) -> Inertia {
-        Inertia { request, config }
+        Inertia {
+            request,
+            config,
+            encrypt_history: None,
+            clear_history: false,
+            headers: HeaderMap::new(),
+            prefetch: Vec::new(),
+            force_reload: false,
+            without_shared: false,
+            merge_props: Vec::new(),
+            first_load_props: Vec::new(),
+            cache_tags: Vec::new(),
+            layout: None,
+            window_data: Map::new(),
+        }
+    }
+
+    /// Makes the client do a full page reload of the current url.
+    ///
+    /// Inertia visits get the same `409 Conflict` response with an
+    /// `X-Inertia-Location` header as an asset version mismatch, so
+    /// the browser reloads the page. Initial page loads are rendered
+    /// as usual.
+    pub fn force_reload(mut self) -> Self {
+        self.force_reload = true;
+        self
+    }
+
+    /// Leaves the [shared props](InertiaConfig::share) out of this
+    /// response, e.g. to keep a polling endpoint's payload small.
+    ///
+    /// Partial reloads asking for a shared key get nothing for it,
+    /// unless the handler's own props include the key.
+    pub fn without_shared(mut self) -> Self {
+        self.without_shared = true;
+        self
+    }
+
+    /// Hints that the client is likely to visit `url` next.
+    ///
+    /// The urls are sent in a `prefetch` array on the page object, so
+    /// they are available on initial page loads as well as Inertia
+    /// visits. This is not part of the Inertia protocol: the client
+    /// must opt in, e.g. by reading `usePage().prefetch` and calling
+    /// `router.prefetch` for each url. May be called multiple times.
+    pub fn prefetch<U: AsRef<str>>(mut self, url: U) -> Self {
+        self.prefetch.push(url.as_ref().to_owned());
+        self
+    }
+
+    /// Adds a prop that the client merges with its current value
+    /// instead of replacing it, e.g. to append the next page of an
+    /// infinite scroll list.
+    ///
+    /// `path` may be dotted (like `users.data`) to merge a nested
+    /// value: `value` is placed at that path in the props, creating
+    /// intermediate objects as needed and overriding whatever the
+    /// props had there. Requires [ProtocolVersion::V2].
+    ///
+    /// More info at: https://inertiajs.com/merging-props
+    pub fn merge<P: AsRef<str>, V: Serialize>(mut self, path: P, value: V) -> Self {
+        let path = path.as_ref().to_owned();
+        let value = serde_json::to_value(value).map_err(|err| err.to_string());
+        self.merge_props.retain(|(existing, _)| *existing != path);
+        self.merge_props.push((path, value));
+        self
+    }
+
+    /// Adds a prop that is only sent on the initial (full HTML) page
+    /// load and left out of all Inertia visits, e.g. for a large config
+    /// blob the client keeps for the whole session.
+    ///
+    /// The client replaces its props on every visit, so the value is
+    /// gone from `usePage().props` after the first one: read it once
+    /// on startup and keep it, e.g. in a store.
+    ///
+    /// On initial page loads the value overrides a prop of the same key
+    /// from the handler or the shared props. On Inertia visits a prop of
+    /// that key is removed, wherever it came from. May be called
+    /// multiple times.
+    pub fn only_on_first_load<K: AsRef<str>, V: Serialize>(mut self, key: K, value: V) -> Self {
+        let key = key.as_ref().to_owned();
+        let value = serde_json::to_value(value).map_err(|err| err.to_string());
+        self.first_load_props
+            .retain(|(existing, _)| *existing != key);
+        self.first_load_props.push((key, value));
+        self
+    }
+
+    /// Adds a header to the response.
+    ///
+    /// Headers managed by the crate (like `X-Inertia`) take precedence.
+    /// `Vary` values are merged with the ones Inertia needs.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Suggests that the client keeps its scroll position, e.g. after
+    /// an action on a long page.
+    ///
+    /// The Inertia protocol has no server-side field for this;
+    /// `preserveScroll` is a visit option picked by the client. So this
+    /// only sets an `X-Preserve-Scroll: true` response header (outside
+    /// of the `X-Inertia-*` headers reserved for the protocol), which
+    /// the client has to opt into reading, e.g. in a `success` event
+    /// listener.
+    ///
+    /// Browsers follow redirects before the client sees the response,
+    /// so the hint only works on the final page response, not on
+    /// [redirects](Self::redirect).
+    pub fn preserve_scroll(mut self) -> Self {
+        self.headers
+            .insert("X-Preserve-Scroll", HeaderValue::from_static("true"));
+        self
+    }
+
+    /// Suggests that the client keeps the page component's local
+    /// state. Like [preserve_scroll](Self::preserve_scroll), this is
+    /// only an `X-Preserve-State: true` header the client has to opt
+    /// into reading, and only on the final, non-redirect response.
+    pub fn preserve_state(mut self) -> Self {
+        self.headers
+            .insert("X-Preserve-State", HeaderValue::from_static("true"));
+        self
+    }
+
+    /// Defines a [window data](InertiaConfig::window_data) value for
+    /// this response only, e.g. feature flags for the current user.
+    ///
+    /// The value overrides a config value of the same key. Like the
+    /// config's values, it is only written on initial page loads.
+    pub fn window_data<K: Into<String>>(mut self, key: K, value: Value) -> Self {
+        self.window_data.insert(key.into(), value);
+        self
+    }
+
+    /// Renders the initial page load with the layout [added
+    /// as](InertiaConfig::add_layout) `name`, whatever the component.
+    pub fn layout<N: Into<String>>(mut self, name: N) -> Self {
+        self.layout = Some(name.into());
+        self
+    }
+
+    /// Tags the initial page load response for a CDN's tag-based
+    /// purging, e.g. with the component or the ids of the records it
+    /// shows. May be called multiple times.
+    ///
+    /// The tags are sent in the
+    /// [cache_tag_header](InertiaConfig::cache_tag_header), joined by
+    /// its separator. Inertia visits aren't tagged. Rendering fails
+    /// with a `500 Internal Server Error` if a tag is empty, contains
+    /// whitespace or the separator, or isn't a valid header value.
+    pub fn cache_tag<T: AsRef<str>>(mut self, tag: T) -> Self {
+        self.cache_tags.push(tag.as_ref().to_owned());
+        self
+    }
+
+    /// Adds a `Set-Cookie` header to the response (a page or a
+    /// [redirect](Self::redirect)). May be called multiple times.
+    ///
+    /// The value is sent as is, so it should carry its own attributes
+    /// (like `SameSite=Lax; Secure; HttpOnly`). The crate itself never
+    /// sets cookies.
+    pub fn cookie(mut self, cookie: HeaderValue) -> Self {
+        self.headers.append(SET_COOKIE, cookie);
+        self
+    }
+
+    /// Builds an [InertiaError] rendering `component` with `status`
+    /// and `message` props, e.g. for the error path of a handler.
+    pub fn error<M: Into<String>>(
+        &self,
+        component: &'static str,
+        status: StatusCode,
+        message: M,
+    ) -> InertiaError {
+        InertiaError::new(self.clone(), component, status, message)
+    }
+
+    /// Redirects to `location`, keeping any headers (like
+    /// [cookies](Self::cookie)) set on this response.
+    ///
+    /// Redirects after non-GET requests use `303 See Other`, so the
+    /// client follows them with a GET as Inertia requires for `PUT`,
+    /// `PATCH` and `DELETE` visits. Other redirects use `302 Found`.
+    ///
+    /// More info at: https://inertiajs.com/redirects
+    pub fn redirect<L: AsRef<str>>(self, location: L) -> axum::response::Response {
+        let status = if self.request.method == Method::GET {
+            StatusCode::FOUND
+        } else {
+            StatusCode::SEE_OTHER
+        };
+        let mut headers = self.headers;
+        match HeaderValue::from_str(location.as_ref()) {
+            Ok(location) => {
+                headers.insert(LOCATION, location);
+                (status, headers).into_response()
+            }
+            Err(_) => {
+                eprintln!("Invalid redirect location: {}", location.as_ref());
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    /// Redirects back to the page the request came from (its
+    /// `Referer`), or to the base path without one.
+    ///
+    /// Useful for actions that keep the user on the current page, like
+    /// "mark as read": after a `POST` the client follows the `303 See
+    /// Other` with a GET of the same page and, with `preserveState`,
+    /// only swaps in the fresh props.
+    ///
+    /// Only the path and query of the referer are used, so a referer
+    /// from another site can't redirect the user off this one.
+    pub fn back(self) -> axum::response::Response {
+        let location = match self.request.referer_path() {
+            Some(path) => path,
+            None => self.config.url_for("/"),
+        };
+        self.redirect(location)
+    }
+
+    /// Tells the client to clear its history state, e.g. after a
+    /// logout.
+    ///
+    /// More info at: https://inertiajs.com/history-encryption
+    pub fn clear_history(mut self) -> Self {
+        self.clear_history = true;
+        self
+    }
+
+    /// Sets whether the history state of this page is encrypted,
+    /// overriding the [InertiaConfig] default.
+    pub fn encrypt_history(mut self, encrypt: bool) -> Self {
+        self.encrypt_history = Some(encrypt);
+        self
     }
 
     /// Renders an Inertia response.
-    pub fn render<S: Props>(self, component: &'static str, props: S) -> Response {
-        let request = self.request;
-        let url = request.url.clone();
-        let page = Page {
-            component,
-            props: props
-                .serialize(request.partial.as_ref())
-                // TODO: error handling
-                .expect("serialization failure"),
-            url,
-            version: self.config.version().clone(),
-        };
-        Response {
-            page,
-            request,
+    ///
+    /// If `props` fail to serialize, the response is a `500 Internal
+    /// Server Error` and the error is logged. Note that json has no
+    /// representation for `NaN` or infinite floats: serde_json turns
+    /// them into `null`, so sanitize such values before rendering if
+    /// the client needs to tell them apart.
+    pub fn render<S: IntoInertiaProps>(self, component: &'static str, props: S) -> Response {
+        match self.into_page(component, props) {
+            Ok((page, context)) => context.render(page),
+            Err(response) => *response,
+        }
+    }
+
+    /// Renders an Inertia response with props assembled from `(key,
+    /// value)` pairs, e.g. when they come from different sources:
+    ///
+    /// ```rust
+    /// use axum_inertia::Inertia;
+    /// use serde_json::json;
+    ///
+    /// async fn handler(i: Inertia) -> axum_inertia::Response {
+    ///     let mut props = vec![("title", json!("Posts"))];
+    ///     props.push(("count", json!(2)));
+    ///     i.render_props("Posts/Index", props)
+    /// }
+    /// ```
+    ///
+    /// Later pairs override earlier ones with the same key. If a value
+    /// fails to serialize, the response is a `500 Internal Server
+    /// Error` and the logged error names its key.
+    pub fn render_props<K, V, I>(self, component: &'static str, pairs: I) -> Response
+    where
+        K: Into<String>,
+        V: Serialize,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.render(component, props::PropPairs::new(pairs))
+    }
+
+    /// Renders `base_component` with `modal_component` shown on top of
+    /// it, for modal routes (e.g. `/users/1/edit` opening over the user
+    /// list).
+    ///
+    /// The modal is sent as a `modal` prop of the base page, next to
+    /// the base page's own props and the [shared
+    /// props](InertiaConfig::share):
+    ///
+    /// ```json
+    /// {
+    ///   "users": [1],
+    ///   "modal": { "component": "Users/Edit", "props": { "user": 1 } }
+    /// }
+    /// ```
+    ///
+    /// This shape is specific to this crate (it is not the one of
+    /// modal libraries like momentum-modal, which also need e.g. a
+    /// `baseURL`), so the base page has to mount the modal itself.
+    /// Partial reloads select from the base page's props and can ask
+    /// for the `modal` prop as a whole.
+    pub fn render_modal<B: Props, S: Props>(
+        self,
+        base_component: &'static str,
+        base_props: B,
+        modal_component: &'static str,
+        modal_props: S,
+    ) -> Response {
+        let props = props::ModalProps {
+            base: base_props,
+            component: self.config.resolve_component(modal_component),
+            props: modal_props,
+        };
+        self.render(base_component, props)
+    }
+
+    /// Builds the page object without rendering it.
+    ///
+    /// This is what [render](Self::render) does internally; use it to
+    /// inspect or transform the [Page] (e.g. for telemetry) and then
+    /// finish the response with [ResponseContext::render]. If the page
+    /// can't be built, the (boxed) error response is returned instead.
+    ///
+    /// ```rust
+    /// use axum_inertia::Inertia;
+    /// use axum::response::{IntoResponse, Response};
+    /// use serde_json::json;
+    ///
+    /// async fn handler(i: Inertia) -> Response {
+    ///     match i.into_page("Pages/Home", json!({})) {
+    ///         Ok((page, context)) => {
+    ///             println!("rendering {}", page.component);
+    ///             context.render(page).into_response()
+    ///         }
+    ///         Err(response) => (*response).into_response(),
+    ///     }
+    /// }
+    /// ```
+    pub fn into_page<S: IntoInertiaProps>(
+        mut self,
+        component: &'static str,
+        props: S,
+    ) -> Result<(Page, ResponseContext), Box<Response>> {
+        let component = self.config.resolve_component(component);
+        if self.config.clears_history_at(&self.request.url) {
+            // Unlike a handler calling `clear_history`, the config
+            // applies to every page at the path, so don't fail them all.
+            if self.config.protocol() >= ProtocolVersion::V2 {
+                self.clear_history = true;
+            } else {
+                eprintln!(
+                    "Warning: clear_history_on requires ProtocolVersion::V2, ignoring it for {}",
+                    self.request.url
+                );
+            }
+        }
+        let cache_tags = self.cache_tags_header(&component);
+        if let Ok(Some(tags)) = &cache_tags {
+            let name = self.config.cache_tag_header_name().clone();
+            self.headers.insert(name, tags.clone());
+        }
+        if self.layout.is_none() {
+            self.layout = self.config.layout_name_for(&component).map(str::to_string);
+        }
+        let encrypt_history = self
+            .encrypt_history
+            .unwrap_or_else(|| self.config.encrypts_history());
+        let page = cache_tags
+            .and_then(|_| self.check_protocol(&component, encrypt_history))
+            .and_then(|()| self.serialize_props(&component, props))
+            .map(|(props, merge_props)| Page {
+                component,
+                props,
+                url: self.config.page_url(&self.request.url),
+                version: self.config.version().clone(),
+                encrypt_history,
+                clear_history: self.clear_history,
+                prefetch: self.prefetch,
+                merge_props,
+            });
+        let context = ResponseContext {
+            request: self.request,
             config: self.config,
+            headers: self.headers,
+            force_reload: self.force_reload,
+            layout: self.layout,
+            window_data: self.window_data,
+        };
+        match page {
+            Ok(page) => Ok((page, context)),
+            Err(err) => Err(Box::new(context.into_response_with(Err(err)))),
+        }
+    }
+
+    /// The [cache tags](Self::cache_tag) header value for initial page
+    /// loads. Fails on tags that can't be sent, rather than dropping
+    /// them and leaving the page unpurgeable.
+    fn cache_tags_header(&self, component: &str) -> Result<Option<HeaderValue>, RenderError> {
+        if self.request.is_xhr || self.cache_tags.is_empty() {
+            return Ok(None);
+        }
+        let separator = self.config.cache_tag_separator();
+        let invalid = self.cache_tags.iter().find(|tag| {
+            tag.is_empty()
+                || tag.contains(char::is_whitespace)
+                || (!separator.trim().is_empty() && tag.contains(separator.trim()))
+                || HeaderValue::from_str(tag).is_err()
+        });
+        if let Some(tag) = invalid {
+            return Err(RenderError::InvalidCacheTag {
+                component: component.to_string(),
+                tag: tag.clone(),
+            });
+        }
+        let tags = HeaderValue::from_str(&self.cache_tags.join(separator))
+            .expect("cache tags were checked to be valid header values");
+        Ok(Some(tags))
+    }
+
+    /// Fails if the page uses a feature the configured protocol
+    /// version doesn't support.
+    fn check_protocol(&self, component: &str, encrypt_history: bool) -> Result<(), RenderError> {
+        if self.config.protocol() >= ProtocolVersion::V2 {
+            return Ok(());
         }
+        let feature = if encrypt_history {
+            "encryptHistory"
+        } else if self.clear_history {
+            "clearHistory"
+        } else if !self.merge_props.is_empty() {
+            "mergeProps"
+        } else {
+            return Ok(());
+        };
+        Err(RenderError::RequiresV2 {
+            component: component.to_string(),
+            feature,
+        })
+    }
+
+    /// Serializes and filters the props, returning them with the
+    /// merge prop paths to announce.
+    fn serialize_props<S: IntoInertiaProps>(
+        &self,
+        component: &str,
+        props: S,
+    ) -> Result<(Value, Vec<String>), RenderError> {
+        // Inertia props must be an object; the client would fail in
+        // confusing ways given e.g. a top-level array.
+        let mut props = props
+            .into_inertia_props(self.request.partial.as_ref())
+            .map_err(|err| match err {
+                PropsError::Serialize(message) => RenderError::Props {
+                    component: component.to_string(),
+                    message,
+                },
+                PropsError::NotAnObject(found) => RenderError::NotAnObject {
+                    component: component.to_string(),
+                    found,
+                },
+            })?;
+        if !self.without_shared && !self.config.shared_props().is_empty() {
+            props = props::merge_shared(
+                props,
+                self.config.shared_props(),
+                self.config.deep_merges_shared(),
+            );
+        }
+        if let Some(token) = self
+            .request
+            .csrf_token
+            .as_ref()
+            .filter(|_| !self.without_shared)
+        {
+            props
+                .entry("csrf_token")
+                .or_insert_with(|| Value::String(token.clone()));
+        }
+        if !self.request.is_xhr {
+            for (key, value) in &self.first_load_props {
+                let value = value.clone().map_err(|message| RenderError::Props {
+                    component: component.to_string(),
+                    message,
+                })?;
+                props.insert(key.clone(), value);
+            }
+        } else {
+            for (key, _) in &self.first_load_props {
+                props.remove(key);
+            }
+        }
+        for (path, value) in &self.merge_props {
+            let value = value.clone().map_err(|message| RenderError::Props {
+                component: component.to_string(),
+                message,
+            })?;
+            props::insert_at_path(&mut props, path, value);
+        }
+        let reload = &self.request.partial_reload;
+        let props = partial::resolve_props(
+            props,
+            reload.data.as_deref(),
+            reload.except.as_deref(),
+            reload.component.as_deref(),
+            component,
+        );
+        let merge_props = partial::resolve_merge_props(
+            self.merge_props
+                .iter()
+                .map(|(path, _)| path.clone())
+                .collect(),
+            &props,
+            &reload.reset,
+        );
+        let mut props = Value::Object(props);
+        if self.config.stringifies_unsafe_integers() {
+            props::stringify_unsafe_integers(&mut props);
+        }
+        if self.config.sorts_props() {
+            props::sort_keys(&mut props);
+        }
+        if let Some((limit, action)) = self.config.props_limit() {
+            let size = serde_json::to_vec(&props).map_or(0, |json| json.len());
+            if size > limit {
+                let err = RenderError::PropsTooLarge {
+                    component: component.to_string(),
+                    size,
+                    limit,
+                };
+                match action {
+                    PropsLimit::Warn => eprintln!("Warning: {err}"),
+                    PropsLimit::Error => return Err(err),
+                }
+            }
+        }
+        Ok((props, merge_props))
     }
 }
 
@@ -254,6 +820,149 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_builds_the_page_before_rendering() {
+        let layout = Box::new(|props| format!(r#"<div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(Some("123".to_string()), layout)
+            .protocol_version(ProtocolVersion::V2)
+            .base_path("/app");
+        let inertia = Inertia::new(request::Request::test_request(), config)
+            .clear_history()
+            .prefetch("/foo/baz");
+
+        let Ok((mut page, context)) = inertia.into_page("Foo", json!({ "bar": "baz" })) else {
+            panic!("failed to build the page");
+        };
+
+        assert_eq!(page.component, "Foo");
+        assert_eq!(page.props, json!({ "bar": "baz" }));
+        assert_eq!(page.url, "/app/foo/bar");
+        assert_eq!(page.version, Some("123".to_string()));
+        assert!(page.clear_history);
+        assert!(!page.encrypt_history);
+        assert_eq!(page.prefetch, vec!["/foo/baz"]);
+
+        page.component = "Bar".to_string();
+        let response = context.render(page).into_response();
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn it_uses_a_custom_request_header_name() {
+        async fn handler(i: Inertia) -> impl IntoResponse {
+            i.render("foo!", json!({"bar": "baz"}))
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+
+        let config = InertiaConfig::new(None, layout)
+            .request_header_name(HeaderName::from_static("x-corp-inertia"));
+
+        let app = Router::new()
+            .route("/test", get(handler))
+            .with_state(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let client = reqwest::Client::new();
+
+        let res = client
+            .get(format!("http://{}/test", &addr))
+            .header("X-Corp-Inertia", "true")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.headers().get("X-Corp-Inertia").unwrap(), "true");
+        assert!(res.headers().get("X-Inertia").is_none());
+        assert_eq!(res.headers().get("Vary").unwrap(), "x-corp-inertia");
+        assert_eq!(
+            res.text().await.unwrap(),
+            r#"{"component":"foo!","props":{"bar":"baz"},"url":"/test","version":null}"#
+        );
+
+        // The standard header is no longer recognized.
+        let res = client
+            .get(format!("http://{}/test", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap();
+        assert!(res.text().await.unwrap().starts_with("<html>"));
+    }
+
+    #[tokio::test]
+    async fn it_works_with_a_layer() {
+        async fn handler(i: Inertia) -> impl IntoResponse {
+            i.render("foo!", json!({"bar": "baz"}))
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+
+        let config = InertiaConfig::new(Some("123".to_string()), layout);
+
+        let app = Router::new()
+            .route("/test", get(handler))
+            .layer(InertiaLayer::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let client = reqwest::Client::new();
+
+        let res = client
+            .get(format!("http://{}/test", &addr))
+            .header("X-Inertia", "true")
+            .header("X-Inertia-Version", "123")
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.text().await.unwrap(),
+            r#"{"component":"foo!","props":{"bar":"baz"},"url":"/test","version":"123"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn it_responds_with_server_error_without_a_config() {
+        async fn handler(i: Inertia) -> impl IntoResponse {
+            i.render("foo!", json!({}))
+        }
+
+        let app = Router::new().route("/test", get(handler));
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let res = reqwest::get(format!("http://{}/test", &addr))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
     #[tokio::test]
     async fn it_responds_with_conflict_on_version_mismatch() {
         async fn handler(i: Inertia) -> impl IntoResponse {
@@ -296,4 +1005,1394 @@ mod tests {
             Some("/test")
         );
     }
+
+    #[tokio::test]
+    async fn it_responds_with_conflict_to_stale_partial_reloads() {
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(Some("123".to_string()), layout);
+        let request = http::Request::builder()
+            .uri("/users")
+            .header("X-Inertia", "true")
+            .header("X-Inertia-Version", "122")
+            .header("X-Inertia-Partial-Component", "Users/Index")
+            .header("X-Inertia-Partial-Data", "users")
+            .body(())
+            .unwrap();
+
+        let Err((status, headers)) =
+            Inertia::from_request_parts(&mut request.into_parts().0, &config).await
+        else {
+            panic!("expected a stale partial reload to be rejected");
+        };
+
+        assert_eq!(status, http::StatusCode::CONFLICT);
+        assert_eq!(headers.get("X-Inertia-Location").unwrap(), "/users");
+    }
+
+    #[tokio::test]
+    async fn it_cooperates_with_other_vary_headers() {
+        async fn handler(i: Inertia) -> impl IntoResponse {
+            i.header(
+                http::header::VARY,
+                HeaderValue::from_static("Accept-Language, x-inertia"),
+            )
+            .render("foo!", json!({"bar": "baz"}))
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+
+        let config = InertiaConfig::new(Some("123".to_string()), layout);
+
+        let app = Router::new()
+            .route("/test", get(handler))
+            .with_state(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let res = reqwest::get(format!("http://{}/test", &addr))
+            .await
+            .unwrap();
+        // The existing fields are kept, in a single header, and
+        // X-Inertia isn't added twice.
+        let vary: Vec<_> = res.headers().get_all("Vary").iter().collect();
+        assert_eq!(vary, ["Accept-Language, x-inertia"]);
+    }
+
+    #[tokio::test]
+    async fn it_overrides_history_flags_per_response() {
+        async fn logout(i: Inertia) -> impl IntoResponse {
+            i.clear_history()
+                .encrypt_history(false)
+                .render("Login", json!({}))
+        }
+
+        async fn dashboard(i: Inertia) -> impl IntoResponse {
+            i.render("Dashboard", json!({}))
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+
+        let config = InertiaConfig::new(None, layout)
+            .protocol_version(ProtocolVersion::V2)
+            .encrypt_history(true);
+
+        let app = Router::new()
+            .route("/logout", get(logout))
+            .route("/dashboard", get(dashboard))
+            .with_state(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let client = reqwest::Client::new();
+
+        let body = client
+            .get(format!("http://{}/logout", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(page["clearHistory"], json!(true));
+        assert_eq!(page.get("encryptHistory"), None);
+
+        let body = client
+            .get(format!("http://{}/dashboard", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(page["encryptHistory"], json!(true));
+        assert_eq!(page.get("clearHistory"), None);
+    }
+
+    #[tokio::test]
+    async fn it_refuses_v2_features_in_v1_mode() {
+        async fn logout(i: Inertia) -> impl IntoResponse {
+            i.clear_history().render("Login", json!({}))
+        }
+
+        async fn dashboard(i: Inertia) -> impl IntoResponse {
+            i.render("Dashboard", json!({}))
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+
+        let config = InertiaConfig::new(None, layout);
+
+        let app = Router::new()
+            .route("/logout", get(logout))
+            .route("/dashboard", get(dashboard))
+            .with_state(config.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let client = reqwest::Client::new();
+
+        let res = client
+            .get(format!("http://{}/logout", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body = client
+            .get(format!("http://{}/dashboard", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(
+            body,
+            r#"{"component":"Dashboard","props":{},"url":"/dashboard","version":null}"#
+        );
+
+        let request = http::Request::builder()
+            .uri("/dashboard")
+            .header("X-Inertia", "true")
+            .body(())
+            .unwrap();
+        let body = testing::render_to_string(
+            &config.encrypt_history(true),
+            request,
+            "Dashboard",
+            json!({}),
+        )
+        .await;
+        assert_eq!(body, "Internal Server Error");
+    }
+
+    #[tokio::test]
+    async fn it_resolves_component_names() {
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+        let config =
+            InertiaConfig::new(None, layout).component_resolver(|name| format!("Admin/{}", name));
+        let request = http::Request::builder()
+            .uri("/users")
+            .header("X-Inertia", "true")
+            .body(())
+            .unwrap();
+
+        let body = testing::render_to_string(&config, request, "Users/Index", json!({})).await;
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(page["component"], json!("Admin/Users/Index"));
+    }
+
+    #[tokio::test]
+    async fn it_includes_custom_headers() {
+        async fn handler(i: Inertia) -> impl IntoResponse {
+            i.header(
+                HeaderName::from_static("x-ratelimit-remaining"),
+                HeaderValue::from_static("42"),
+            )
+            .header(
+                http::header::VARY,
+                HeaderValue::from_static("Accept-Language"),
+            )
+            .render("foo!", json!({"bar": "baz"}))
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+
+        let config = InertiaConfig::new(Some("123".to_string()), layout);
+
+        let app = Router::new()
+            .route("/test", get(handler))
+            .with_state(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let client = reqwest::Client::new();
+
+        let res = client
+            .get(format!("http://{}/test", &addr))
+            .header("X-Inertia", "true")
+            .header("X-Inertia-Version", "123")
+            .send()
+            .await
+            .unwrap();
+        let headers = res.headers();
+
+        assert_eq!(headers.get("X-Ratelimit-Remaining").unwrap(), "42");
+        assert_eq!(headers.get("X-Inertia").unwrap(), "true");
+        assert_eq!(headers.get("X-Inertia-Version").unwrap(), "123");
+        assert_eq!(headers.get("Vary").unwrap(), "Accept-Language, X-Inertia");
+        assert_eq!(headers.get("Content-Type").unwrap(), "application/json");
+    }
+
+    #[tokio::test]
+    async fn it_responds_with_server_error_when_props_fail_to_serialize() {
+        struct Unserializable;
+
+        impl serde::Serialize for Unserializable {
+            fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom("unserializable prop"))
+            }
+        }
+
+        #[derive(serde::Serialize)]
+        struct PageProps {
+            broken: Unserializable,
+        }
+
+        async fn handler(i: Inertia) -> impl IntoResponse {
+            i.render(
+                "foo!",
+                PageProps {
+                    broken: Unserializable,
+                },
+            )
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+
+        let config = InertiaConfig::new(None, layout);
+
+        let app = Router::new()
+            .route("/test", get(handler))
+            .with_state(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let res = reqwest::get(format!("http://{}/test", &addr))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!res.text().await.unwrap().contains("data-page"));
+    }
+
+    #[tokio::test]
+    async fn it_prefixes_urls_with_the_base_path() {
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(None, layout).base_path("/app/");
+        let request = http::Request::builder()
+            .uri("/users")
+            .header("X-Inertia", "true")
+            .body(())
+            .unwrap();
+
+        let body = testing::render_to_string(&config, request, "Users/Index", json!({})).await;
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(page["url"], json!("/app/users"));
+    }
+
+    #[tokio::test]
+    async fn it_rejects_props_that_are_not_an_object() {
+        #[derive(serde::Serialize)]
+        struct PageProps {
+            posts: Vec<&'static str>,
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(None, layout);
+        let request = || {
+            http::Request::builder()
+                .uri("/posts")
+                .header("X-Inertia", "true")
+                .body(())
+                .unwrap()
+        };
+
+        let body = testing::render_to_string(
+            &config,
+            request(),
+            "Posts/Index",
+            PageProps {
+                posts: vec!["post one"],
+            },
+        )
+        .await;
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(page["props"], json!({ "posts": ["post one"] }));
+
+        let body =
+            testing::render_to_string(&config, request(), "Posts/Index", vec!["post one"]).await;
+        assert_eq!(body, "Internal Server Error");
+
+        let body = testing::render_to_string(&config, request(), "Posts/Index", "post one").await;
+        assert_eq!(body, "Internal Server Error");
+
+        let inertia = Inertia::new(request::Request::test_request(), config);
+        let Err(response) = inertia.into_page("Posts/Index", json!("post one")) else {
+            panic!("expected string props to be rejected");
+        };
+        assert_eq!(
+            response.page.err().unwrap().to_string(),
+            "props for Posts/Index must serialize to a json object, found a string"
+        );
+    }
+
+    #[test]
+    fn it_merges_props_at_nested_paths() {
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(None, layout).protocol_version(ProtocolVersion::V2);
+        let inertia = Inertia::new(request::Request::test_request(), config.clone())
+            .merge("users.data", json!([{ "id": 3 }, { "id": 4 }]))
+            .merge("tags", json!(["new"]));
+
+        let Ok((page, _)) = inertia.into_page("Users/Index", json!({ "users": { "page": 2 } }))
+        else {
+            panic!("failed to build the page");
+        };
+
+        assert_eq!(page.merge_props, vec!["users.data", "tags"]);
+        assert_eq!(
+            page.props,
+            json!({
+                "users": { "page": 2, "data": [{ "id": 3 }, { "id": 4 }] },
+                "tags": ["new"],
+            })
+        );
+        let page = serde_json::to_value(&page).unwrap();
+        assert_eq!(page["mergeProps"], json!(["users.data", "tags"]));
+
+        let config = config.protocol_version(ProtocolVersion::V1);
+        let inertia =
+            Inertia::new(request::Request::test_request(), config).merge("users.data", json!([]));
+        let response = inertia.render("Users/Index", json!({})).into_response();
+        assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn it_suggests_preserving_scroll_and_state() {
+        let config = InertiaConfig::new(None, Box::new(|props| props));
+
+        let response = Inertia::new(request::Request::test_request(), config.clone())
+            .preserve_scroll()
+            .preserve_state()
+            .render("Foo", json!({}))
+            .into_response();
+        assert_eq!(response.headers()["X-Preserve-Scroll"], "true");
+        assert_eq!(response.headers()["X-Preserve-State"], "true");
+
+        let response = Inertia::new(request::Request::test_request(), config)
+            .render("Foo", json!({}))
+            .into_response();
+        assert!(response.headers().get("X-Preserve-Scroll").is_none());
+        assert!(response.headers().get("X-Preserve-State").is_none());
+    }
+
+    #[test]
+    fn it_sends_cache_tags_on_initial_page_loads() {
+        let config = InertiaConfig::new(None, Box::new(|props| props));
+        let full_load = || request::Request {
+            is_xhr: false,
+            ..request::Request::test_request()
+        };
+
+        let response = Inertia::new(full_load(), config.clone())
+            .cache_tag("posts")
+            .cache_tag("post-42")
+            .render("Posts/Show", json!({}))
+            .into_response();
+        assert_eq!(response.headers()["Surrogate-Key"], "posts post-42");
+
+        let response = Inertia::new(
+            full_load(),
+            config
+                .clone()
+                .cache_tag_header(HeaderName::from_static("cache-tag"), ","),
+        )
+        .cache_tag("posts")
+        .cache_tag("post-42")
+        .render("Posts/Show", json!({}))
+        .into_response();
+        assert_eq!(response.headers()["Cache-Tag"], "posts,post-42");
+
+        let response = Inertia::new(full_load(), config.clone())
+            .cache_tag("posts")
+            .cache_tag("two words")
+            .render("Posts/Show", json!({}))
+            .into_response();
+        assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.headers().get("Surrogate-Key").is_none());
+
+        let response = Inertia::new(request::Request::test_request(), config)
+            .cache_tag("posts")
+            .render("Posts/Show", json!({}))
+            .into_response();
+        assert!(response.headers().get("Surrogate-Key").is_none());
+    }
+
+    #[tokio::test]
+    async fn it_renders_modals_over_a_base_page() {
+        let config = InertiaConfig::new(None, Box::new(|props| props)).share("app", json!("Blog"));
+
+        let response = Inertia::new(request::Request::test_request(), config)
+            .render_modal(
+                "Users/Index",
+                json!({ "users": [1] }),
+                "Users/Edit",
+                json!({ "user": { "id": 1 } }),
+            )
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(page["component"], "Users/Index");
+        assert_eq!(
+            page["props"],
+            json!({
+                "app": "Blog",
+                "users": [1],
+                "modal": { "component": "Users/Edit", "props": { "user": { "id": 1 } } },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn it_selects_layouts_per_component() {
+        let config = InertiaConfig::new(None, Box::new(|_| "app".to_string()))
+            .add_layout("marketing", Box::new(|_| "marketing".to_string()))
+            .layout_for("Marketing/", "marketing");
+        let request = || http::Request::builder().uri("/").body(()).unwrap();
+
+        let html = testing::render_to_string(&config, request(), "Marketing/Home", json!({})).await;
+        assert_eq!(html, "marketing");
+
+        let html = testing::render_to_string(&config, request(), "Dashboard", json!({})).await;
+        assert_eq!(html, "app");
+
+        let full_load = || request::Request {
+            is_xhr: false,
+            ..request::Request::test_request()
+        };
+        let render = |inertia: Inertia| async move {
+            let response = inertia.render("Dashboard", json!({})).into_response();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, String::from_utf8(body.to_vec()).unwrap())
+        };
+
+        let (_, html) = render(Inertia::new(full_load(), config.clone()).layout("marketing")).await;
+        assert_eq!(html, "marketing");
+
+        let (status, _) = render(Inertia::new(full_load(), config).layout("missing")).await;
+        assert_eq!(status, http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn it_redirects_back() {
+        let config = InertiaConfig::new(None, Box::new(|props| props)).base_path("/app");
+        let request = request::Request {
+            method: http::Method::POST,
+            referer: Some("http://localhost/app/inbox?page=2".to_string()),
+            ..request::Request::test_request()
+        };
+
+        let response = Inertia::new(request, config.clone()).back();
+        assert_eq!(response.status(), http::StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers().get(LOCATION).unwrap(),
+            "/app/inbox?page=2"
+        );
+
+        for (referer, location) in [
+            ("https://evil.example.com/phish?x=1", "/phish?x=1"),
+            ("//evil.example.com/phish", "/app/"),
+            ("javascript:alert(1)", "/app/"),
+        ] {
+            let request = request::Request {
+                method: http::Method::POST,
+                referer: Some(referer.to_string()),
+                ..request::Request::test_request()
+            };
+            let response = Inertia::new(request, config.clone()).back();
+            assert_eq!(response.headers().get(LOCATION).unwrap(), location);
+        }
+
+        let request = request::Request {
+            method: http::Method::POST,
+            ..request::Request::test_request()
+        };
+        let response = Inertia::new(request, config).back();
+        assert_eq!(response.status(), http::StatusCode::SEE_OTHER);
+        assert_eq!(response.headers().get(LOCATION).unwrap(), "/app/");
+    }
+
+    #[tokio::test]
+    async fn it_redirects_with_cookies() {
+        async fn login(i: Inertia) -> impl IntoResponse {
+            i.cookie(HeaderValue::from_static("session=abc; HttpOnly"))
+                .cookie(HeaderValue::from_static("remember=1"))
+                .redirect("/dashboard")
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+
+        let config = InertiaConfig::new(None, layout);
+
+        let app = Router::new()
+            .route("/login", get(login).post(login))
+            .with_state(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+
+        let res = client
+            .post(format!("http://{}/login", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        assert_eq!(res.headers().get("Location").unwrap(), "/dashboard");
+        let cookies: Vec<_> = res.headers().get_all("Set-Cookie").iter().collect();
+        assert_eq!(cookies, vec!["session=abc; HttpOnly", "remember=1"]);
+
+        let res = client
+            .get(format!("http://{}/login", &addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FOUND);
+        assert_eq!(res.headers().get_all("Set-Cookie").iter().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn it_includes_shared_props() {
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(None, layout)
+            .share("app", json!("Blog"))
+            .share("user", json!(null));
+        let request = || {
+            http::Request::builder()
+                .uri("/posts")
+                .header("X-Inertia", "true")
+                .body(())
+                .unwrap()
+        };
+
+        let body = testing::render_to_string(
+            &config,
+            request(),
+            "Posts/Index",
+            json!({ "user": "ada", "posts": [] }),
+        )
+        .await;
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(
+            page["props"],
+            json!({ "app": "Blog", "user": "ada", "posts": [] })
+        );
+    }
+
+    #[test]
+    fn it_leaves_out_shared_props_when_asked() {
+        let config = InertiaConfig::new(None, Box::new(|props| props)).share("app", json!("Blog"));
+        let request = request::Request {
+            partial_reload: partial::PartialReload {
+                component: Some("Posts/Index".to_string()),
+                data: Some(vec!["app".to_string(), "posts".to_string()]),
+                ..Default::default()
+            },
+            ..request::Request::test_request()
+        };
+
+        let Ok((page, _)) = Inertia::new(request::Request::test_request(), config.clone())
+            .without_shared()
+            .into_page("Posts/Index", json!({ "posts": [] }))
+        else {
+            panic!("failed to build the page");
+        };
+        assert_eq!(page.props, json!({ "posts": [] }));
+
+        let Ok((page, _)) = Inertia::new(request, config)
+            .without_shared()
+            .into_page("Posts/Index", json!({ "posts": [] }))
+        else {
+            panic!("failed to build the page");
+        };
+        assert_eq!(page.props, json!({ "posts": [] }));
+    }
+
+    #[test]
+    fn it_sends_first_load_props_only_on_full_loads() {
+        let config = InertiaConfig::new(None, Box::new(|props| props));
+        let full_load = request::Request {
+            is_xhr: false,
+            ..request::Request::test_request()
+        };
+
+        let Ok((page, _)) = Inertia::new(full_load, config.clone())
+            .only_on_first_load("settings", json!({ "locale": "en" }))
+            .into_page("Home", json!({ "posts": [] }))
+        else {
+            panic!("failed to build the page");
+        };
+        assert_eq!(
+            page.props,
+            json!({ "posts": [], "settings": { "locale": "en" } })
+        );
+
+        let Ok((page, _)) = Inertia::new(request::Request::test_request(), config.clone())
+            .only_on_first_load("settings", json!({ "locale": "en" }))
+            .into_page("Home", json!({ "posts": [] }))
+        else {
+            panic!("failed to build the page");
+        };
+        assert_eq!(page.props, json!({ "posts": [] }));
+
+        // Also left out when the handler or shared props have the key.
+        let config = config.share("settings", json!({ "locale": "de" }));
+        let Ok((page, _)) = Inertia::new(request::Request::test_request(), config)
+            .only_on_first_load("settings", json!({ "locale": "en" }))
+            .into_page("Home", json!({ "posts": [], "settings": {} }))
+        else {
+            panic!("failed to build the page");
+        };
+        assert_eq!(page.props, json!({ "posts": [] }));
+    }
+
+    #[test]
+    fn it_normalizes_trailing_slashes_of_the_page_url() {
+        use config::TrailingSlash;
+
+        let page_url = |trailing_slash, url: &str| {
+            let config =
+                InertiaConfig::new(None, Box::new(|props| props)).trailing_slash(trailing_slash);
+            let request = request::Request {
+                url: url.to_string(),
+                ..request::Request::test_request()
+            };
+            let Ok((page, _)) = Inertia::new(request, config).into_page("Users", json!({})) else {
+                panic!("failed to build the page");
+            };
+            page.url
+        };
+
+        assert_eq!(page_url(TrailingSlash::Preserve, "/users/"), "/users/");
+        assert_eq!(page_url(TrailingSlash::Preserve, "/users"), "/users");
+        assert_eq!(page_url(TrailingSlash::Strip, "/users/"), "/users");
+        assert_eq!(page_url(TrailingSlash::Strip, "/users"), "/users");
+        assert_eq!(
+            page_url(TrailingSlash::Strip, "/users/?page=2"),
+            "/users?page=2"
+        );
+        assert_eq!(page_url(TrailingSlash::Strip, "/"), "/");
+        assert_eq!(page_url(TrailingSlash::Enforce, "/users"), "/users/");
+        assert_eq!(page_url(TrailingSlash::Enforce, "/users/"), "/users/");
+        assert_eq!(
+            page_url(TrailingSlash::Enforce, "/users?page=2"),
+            "/users/?page=2"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_reports_response_metrics() {
+        use std::sync::{Arc, Mutex};
+
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let config = InertiaConfig::new(None, Box::new(|props| props)).on_response({
+            let recorded = recorded.clone();
+            move |metrics: &ResponseMetrics| recorded.lock().unwrap().push(metrics.clone())
+        });
+
+        let body = testing::render_to_string(
+            &config,
+            http::Request::builder().uri("/users").body(()).unwrap(),
+            "Users",
+            json!({ "users": [] }),
+        )
+        .await;
+        testing::render_to_string(
+            &config,
+            http::Request::builder()
+                .uri("/users")
+                .header("X-Inertia", "true")
+                .header("X-Inertia-Partial-Component", "Users")
+                .header("X-Inertia-Partial-Data", "users")
+                .body(())
+                .unwrap(),
+            "Users",
+            json!({ "users": [] }),
+        )
+        .await;
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].component, "Users");
+        assert!(!recorded[0].partial);
+        assert_eq!(recorded[0].body_size, body.len() as u64);
+        assert_eq!(recorded[0].status, http::StatusCode::OK);
+        assert!(recorded[1].partial);
+        assert_eq!(recorded[1].props, None);
+    }
+
+    #[tokio::test]
+    async fn it_audits_filtered_props() {
+        use std::sync::{Arc, Mutex};
+
+        let audited = Arc::new(Mutex::new(None));
+        let config = InertiaConfig::new(None, Box::new(|props| props))
+            .share("app", json!("Blog"))
+            .audit_props(true)
+            .on_response({
+                let audited = audited.clone();
+                move |metrics: &ResponseMetrics| *audited.lock().unwrap() = metrics.props.clone()
+            });
+
+        testing::render_to_string(
+            &config,
+            http::Request::builder()
+                .uri("/users")
+                .header("X-Inertia", "true")
+                .header("X-Inertia-Partial-Component", "Users")
+                .header("X-Inertia-Partial-Data", "users,app")
+                .body(())
+                .unwrap(),
+            "Users",
+            json!({ "users": [1], "secret": "hidden" }),
+        )
+        .await;
+
+        let audited = audited.lock().unwrap().clone().unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&audited).unwrap(),
+            json!({ "users": [1], "app": "Blog" })
+        );
+    }
+
+    #[tokio::test]
+    async fn it_ignores_clear_history_paths_for_v1_clients() {
+        let config = InertiaConfig::new(None, Box::new(|props| props)).clear_history_on("/login");
+        let request = http::Request::builder()
+            .uri("/login")
+            .header("X-Inertia", "true")
+            .body(())
+            .unwrap();
+
+        let body = testing::render_to_string(&config, request, "Login", json!({})).await;
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(page["component"], json!("Login"));
+        assert_eq!(page.get("clearHistory"), None);
+    }
+
+    #[tokio::test]
+    async fn it_clears_history_after_redirects_to_configured_paths() {
+        async fn logout(i: Inertia) -> axum::response::Response {
+            i.redirect("/login")
+        }
+
+        async fn login(i: Inertia) -> Response {
+            i.render("Login", json!({}))
+        }
+
+        let config = InertiaConfig::new(None, Box::new(|props| props))
+            .protocol_version(ProtocolVersion::V2)
+            .clear_history_on("/login");
+
+        let app = Router::new()
+            .route("/logout", axum::routing::post(logout))
+            .route("/login", get(login))
+            .route(
+                "/home",
+                get(|i: Inertia| async move { i.render("Home", json!({})) }),
+            )
+            .with_state(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let client = reqwest::Client::new();
+
+        let res = client
+            .post(format!("http://{}/logout", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap();
+        let page: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+        assert_eq!(page["component"], json!("Login"));
+        assert_eq!(page["clearHistory"], json!(true));
+
+        let res = client
+            .get(format!("http://{}/home", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap();
+        let page: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+        assert_eq!(page.get("clearHistory"), None);
+    }
+
+    #[tokio::test]
+    async fn it_exposes_the_csrf_token() {
+        #[derive(Clone)]
+        struct CsrfToken(String);
+
+        let layout = Box::new(|props| {
+            format!(
+                r#"<html><head><title>App</title></head><body><div id="app" data-page='{}'></div></body></html>"#,
+                props
+            )
+        });
+        let config = InertiaConfig::new(None, layout).csrf_token(|parts| {
+            parts
+                .extensions
+                .get::<CsrfToken>()
+                .map(|token| token.0.clone())
+        });
+        let mut request = http::Request::builder().uri("/").body(()).unwrap();
+        request
+            .extensions_mut()
+            .insert(CsrfToken("t0k3n".to_string()));
+
+        let html = testing::render_to_string(&config, request, "Home", json!({})).await;
+
+        assert!(html.contains(r#"<meta name="csrf-token" content="t0k3n"></head>"#));
+        assert!(html.contains(r#""props":{"csrf_token":"t0k3n"}"#));
+
+        let request = http::Request::builder().uri("/").body(()).unwrap();
+        let html = testing::render_to_string(&config, request, "Home", json!({})).await;
+        assert!(!html.contains("csrf"));
+    }
+
+    #[test]
+    fn it_limits_the_size_of_props() {
+        use std::sync::{Arc, Mutex};
+
+        let config = || InertiaConfig::new(None, Box::new(|props| props));
+        let props = json!({ "rows": vec!["a row of a table dump"; 100] });
+
+        let Err(response) = Inertia::new(
+            request::Request::test_request(),
+            config().max_props_bytes(1024, PropsLimit::Error),
+        )
+        .into_page("Table", props.clone()) else {
+            panic!("rendered props over the limit");
+        };
+        assert_eq!(
+            response.into_response().status(),
+            http::StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        let rendered = Inertia::new(
+            request::Request::test_request(),
+            config().max_props_bytes(1024, PropsLimit::Warn),
+        )
+        .into_page("Table", props.clone());
+        assert!(rendered.is_ok());
+
+        let too_large = Arc::new(Mutex::new(Vec::new()));
+        let warn_config = |limit| {
+            let too_large = too_large.clone();
+            config()
+                .max_props_bytes(limit, PropsLimit::Warn)
+                .on_response(move |metrics: &ResponseMetrics| {
+                    too_large.lock().unwrap().push(metrics.props_too_large)
+                })
+        };
+        for limit in [1024, 4096] {
+            let response = Inertia::new(request::Request::test_request(), warn_config(limit))
+                .render("Table", props.clone())
+                .into_response();
+            assert_eq!(response.status(), http::StatusCode::OK);
+        }
+        assert_eq!(*too_large.lock().unwrap(), [true, false]);
+
+        let rendered = Inertia::new(
+            request::Request::test_request(),
+            config().max_props_bytes(4096, PropsLimit::Error),
+        )
+        .into_page("Table", props);
+        assert!(rendered.is_ok());
+    }
+
+    #[tokio::test]
+    async fn it_takes_the_lang_from_a_prop() {
+        async fn handler(i: Inertia) -> Response {
+            i.render("Home", json!({ "locale": "de" }))
+        }
+
+        let app = Router::new().route("/", get(handler)).with_state(
+            vite::Development::default()
+                .lang("en")
+                .into_config()
+                .lang_from_prop("locale"),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let html = reqwest::get(format!("http://{}/", &addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert!(html.starts_with(r#"<!DOCTYPE html><html lang="de">"#));
+
+        let config = InertiaConfig::new(
+            None,
+            Box::new(|props| format!("<html><body>{}</body></html>", props)),
+        )
+        .lang_from_prop("locale");
+        let html = testing::render_to_string(
+            &config,
+            http::Request::builder().uri("/").body(()).unwrap(),
+            "Home",
+            json!({ "locale": "fr" }),
+        )
+        .await;
+        assert!(html.starts_with(r#"<html lang="fr"><body>"#));
+
+        let config = InertiaConfig::new(
+            None,
+            Box::new(|props| format!("<html lang='en'><body>{}</body></html>", props)),
+        )
+        .lang_from_prop("locale");
+        let html = testing::render_to_string(
+            &config,
+            http::Request::builder().uri("/").body(()).unwrap(),
+            "Home",
+            json!({ "locale": "fr" }),
+        )
+        .await;
+        assert!(html.starts_with(r#"<html lang='fr'><body>"#));
+    }
+
+    #[tokio::test]
+    async fn it_takes_the_lang_from_a_prop_in_templates() {
+        let mut engine = tera::Tera::default();
+        engine
+            .add_raw_template(
+                "layout.html",
+                r#"<html lang="{{ lang }}"><body>{{ application | safe }}</body></html>"#,
+            )
+            .unwrap();
+        let config = vite::Development::default()
+            .lang("en")
+            .template_engine(engine, "layout.html")
+            .into_config()
+            .lang_from_prop("locale");
+        let request = || http::Request::builder().uri("/").body(()).unwrap();
+
+        let html = testing::render_to_string(&config, request(), "Home", json!({})).await;
+        assert!(html.starts_with(r#"<html lang="en"><body>"#));
+
+        let html =
+            testing::render_to_string(&config, request(), "Home", json!({ "locale": "de" })).await;
+        assert!(html.starts_with(r#"<html lang="de"><body>"#));
+    }
+
+    #[tokio::test]
+    async fn it_encodes_the_data_page() {
+        use base64::{prelude::BASE64_STANDARD, Engine};
+        use config::DataPageEncoding;
+
+        let layout = || Box::new(|props| format!(r#"<div id="app" data-page='{}'></div>"#, props));
+        let props = json!({ "quote": "\"hi\" & <bye>", "name": "Zoë" });
+        let request = || http::Request::builder().uri("/").body(()).unwrap();
+        let data_page = |html: String| {
+            html.strip_prefix(r#"<div id="app" data-page='"#)
+                .and_then(|html| html.strip_suffix("'></div>"))
+                .unwrap()
+                .to_string()
+        };
+
+        let config = InertiaConfig::new(None, layout()).data_page_encoding(DataPageEncoding::Json);
+        let html = testing::render_to_string(&config, request(), "Home", props.clone()).await;
+        let page: serde_json::Value = serde_json::from_str(&data_page(html)).unwrap();
+        assert_eq!(page["props"], props);
+
+        let config =
+            InertiaConfig::new(None, layout()).data_page_encoding(DataPageEncoding::Base64);
+        let html = testing::render_to_string(&config, request(), "Home", props.clone()).await;
+        let decoded = BASE64_STANDARD.decode(data_page(html)).unwrap();
+        let page: serde_json::Value = serde_json::from_slice(&decoded).unwrap();
+        assert_eq!(page["props"], props);
+    }
+
+    #[tokio::test]
+    async fn it_takes_the_title_from_a_prop() {
+        let layout = Box::new(|props| {
+            format!(
+                r#"<html><head><title>Default</title></head><body><div id="app" data-page='{}'></div></body></html>"#,
+                props
+            )
+        });
+        let config = InertiaConfig::new(None, layout).title_from_prop("meta.title");
+        let request = || http::Request::builder().uri("/posts").body(()).unwrap();
+
+        let html = testing::render_to_string(
+            &config,
+            request(),
+            "Posts/Index",
+            json!({ "meta": { "title": "Posts & Drafts" } }),
+        )
+        .await;
+        assert!(html.contains("<title>Posts &amp; Drafts</title>"));
+        assert!(!html.contains("Default"));
+
+        let html =
+            testing::render_to_string(&config, request(), "Posts/Index", json!({ "meta": {} }))
+                .await;
+        assert!(html.contains("<title>Default</title>"));
+    }
+
+    #[tokio::test]
+    async fn it_defines_window_data() {
+        fn layout(props: String) -> String {
+            format!(
+                r#"<html><head><script src="/main.js"></script></head><body><div id="app" data-page='{}'></div></body></html>"#,
+                props
+            )
+        }
+        let config = InertiaConfig::new(None, Box::new(layout))
+            .window_data("dsn", json!("https://sentry.example.com/1"))
+            .window_data("motd", json!("</script><script>alert(1)</script>"));
+        let request = || http::Request::builder().uri("/").body(()).unwrap();
+
+        let html = testing::render_to_string(&config, request(), "Home", json!({})).await;
+        assert!(html.contains(
+            r#"<head><script>window["__APP_CONFIG__"] = {"dsn":"https://sentry.example.com/1","motd":"\u003c/script\u003e\u003cscript\u003ealert(1)\u003c/script\u003e"};</script><script src="/main.js">"#
+        ));
+
+        let config = config.window_variable("__FLAGS__");
+        let html = testing::render_to_string(&config, request(), "Home", json!({})).await;
+        assert!(html.contains(r#"<script>window["__FLAGS__"] = "#));
+
+        let render = |inertia: Inertia| async move {
+            let response = inertia.render("Home", json!({})).into_response();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+        let full_load = || request::Request {
+            is_xhr: false,
+            ..request::Request::test_request()
+        };
+
+        let html = render(
+            Inertia::new(full_load(), config.clone())
+                .window_data("dsn", json!("https://sentry.example.com/2"))
+                .window_data("beta", json!(true)),
+        )
+        .await;
+        assert!(html.contains(r#""beta":true"#));
+        assert!(html.contains(r#""dsn":"https://sentry.example.com/2""#));
+
+        // Also written when the config has no window data.
+        let html = render(
+            Inertia::new(full_load(), InertiaConfig::new(None, Box::new(layout)))
+                .window_data("beta", json!(true)),
+        )
+        .await;
+        assert!(
+            html.contains(r#"<head><script>window["__APP_CONFIG__"] = {"beta":true};</script>"#)
+        );
+
+        // The config itself is left as is.
+        let html = testing::render_to_string(&config, request(), "Home", json!({})).await;
+        assert!(html.contains(r#"{"dsn":"https://sentry.example.com/1","motd":"#));
+    }
+
+    #[tokio::test]
+    async fn it_serves_props_as_json_to_api_clients() {
+        let layout = Box::new(|props| format!(r#"<div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(None, layout).json_api(true);
+        let request = |accept| {
+            http::Request::builder()
+                .uri("/posts")
+                .header("Accept", accept)
+                .body(())
+                .unwrap()
+        };
+        let props = || json!({ "posts": [] });
+
+        let body =
+            testing::render_to_string(&config, request("application/json"), "Posts", props()).await;
+        assert_eq!(body, r#"{"posts":[]}"#);
+
+        let body =
+            testing::render_to_string(&config, request("text/html, */*"), "Posts", props()).await;
+        assert!(body.starts_with(r#"<div id="app""#));
+
+        let accept = "application/json;q=0.9, text/html";
+        let body = testing::render_to_string(&config, request(accept), "Posts", props()).await;
+        assert!(body.starts_with(r#"<div id="app""#));
+
+        let mut inertia_visit = request("application/json");
+        inertia_visit
+            .headers_mut()
+            .insert("X-Inertia", HeaderValue::from_static("true"));
+        let body = testing::render_to_string(&config, inertia_visit, "Posts", props()).await;
+        assert!(body.contains(r#""component":"Posts""#));
+
+        let config = InertiaConfig::new(None, Box::new(|props| props));
+        let body =
+            testing::render_to_string(&config, request("application/json"), "Posts", props()).await;
+        assert!(body.contains(r#""component":"Posts""#));
+    }
+
+    #[tokio::test]
+    async fn it_renders_errors_returned_from_handlers() {
+        async fn handler(i: Inertia) -> Result<Response, InertiaError> {
+            let post: Option<&str> = None;
+            let post = post
+                .ok_or_else(|| i.error("Error", http::StatusCode::NOT_FOUND, "Post not found"))?;
+            Ok(i.render("Posts/Show", json!({ "post": post })))
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+
+        let config = InertiaConfig::new(None, layout);
+
+        let app = Router::new()
+            .route("/posts/1", get(handler))
+            .with_state(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let client = reqwest::Client::new();
+
+        let res = client
+            .get(format!("http://{}/posts/1", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers().get("X-Inertia").unwrap(), "true");
+        let page: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+        assert_eq!(page["component"], json!("Error"));
+        assert_eq!(
+            page["props"],
+            json!({ "status": 404, "message": "Post not found" })
+        );
+    }
+
+    #[tokio::test]
+    async fn it_includes_prefetch_hints() {
+        async fn handler(i: Inertia) -> impl IntoResponse {
+            i.prefetch("/posts/2")
+                .prefetch("/posts/3")
+                .render("Posts/Show", json!({}))
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(None, layout);
+        let app = Router::new()
+            .route("/posts/1", get(handler))
+            .with_state(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let body = reqwest::Client::new()
+            .get(format!("http://{}/posts/1", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(page["prefetch"], json!(["/posts/2", "/posts/3"]));
+    }
+
+    #[tokio::test]
+    async fn it_forces_a_full_reload() {
+        async fn handler(i: Inertia) -> impl IntoResponse {
+            i.force_reload().render("foo!", json!({"bar": "baz"}))
+        }
+
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(None, layout);
+        let app = Router::new()
+            .route("/test", get(handler))
+            .with_state(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind ephemeral socket");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("server error");
+        });
+
+        let client = reqwest::Client::new();
+
+        let res = client
+            .get(format!("http://{}/test?page=2", &addr))
+            .header("X-Inertia", "true")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            res.headers()
+                .get("X-Inertia-Location")
+                .map(|h| h.to_str().unwrap()),
+            Some("/test?page=2")
+        );
+
+        let res = client
+            .get(format!("http://{}/test", &addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.text().await.unwrap().contains("data-page"));
+    }
+
+    // Without `preserve_order` serde_json sorts keys anyway.
+    #[tokio::test]
+    #[cfg(feature = "preserve_order")]
+    async fn it_sorts_props_when_configured() {
+        let request = || {
+            http::Request::builder()
+                .uri("/users")
+                .header("X-Inertia", "true")
+                .body(())
+                .unwrap()
+        };
+        let props = || props::PropPairs::new(vec![("users", json!([])), ("count", json!(0))]);
+
+        let config = InertiaConfig::new(None, Box::new(|props| props));
+        let body = testing::render_to_string(&config, request(), "Users", props()).await;
+        assert!(body.contains(r#""props":{"users":[],"count":0}"#));
+
+        let config = config.sort_props(true);
+        let body = testing::render_to_string(&config, request(), "Users", props()).await;
+        assert!(body.contains(r#""props":{"count":0,"users":[]}"#));
+    }
+
+    #[tokio::test]
+    async fn it_stringifies_unsafe_integers_when_configured() {
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(None, layout).stringify_unsafe_integers(true);
+        let request = http::Request::builder()
+            .uri("/users/1")
+            .header("X-Inertia", "true")
+            .body(())
+            .unwrap();
+
+        let body = testing::render_to_string(
+            &config,
+            request,
+            "Users/Show",
+            json!({ "id": 1_152_921_504_606_846_976u64, "age": 42 }),
+        )
+        .await;
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(
+            page["props"],
+            json!({ "id": "1152921504606846976", "age": 42 })
+        );
+    }
 }
@@ -6,11 +6,51 @@ use serde_json::Value;
 /// Serializes to json. Included in the `data-page` attribute of the
 /// initial html page, or sent as the payload for Inertia requests.
 ///
+/// Obtain one with [Inertia::into_page](crate::Inertia::into_page) to
+/// inspect or transform it before it is sent. New protocol fields may
+/// be added, so it can't be constructed outside the crate.
+///
 /// More info at: https://inertiajs.com/the-protocol#the-page-object
-#[derive(Serialize)]
-pub(crate) struct Page {
-    pub(crate) component: &'static str,
-    pub(crate) props: Value,
-    pub(crate) url: String,
-    pub(crate) version: Option<String>,
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct Page {
+    /// The (resolved) name of the component to render.
+    pub component: String,
+    /// The serialized page props, a json object.
+    pub props: Value,
+    /// The page url, including the query string and any base path.
+    pub url: String,
+    /// The asset version.
+    pub version: Option<String>,
+    /// Whether the client encrypts the history state (v2 only).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub encrypt_history: bool,
+    /// Whether the client clears its history state (v2 only).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub clear_history: bool,
+    /// Urls the client is likely to visit next. Not part of the
+    /// protocol; see [Inertia::prefetch](crate::Inertia::prefetch).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prefetch: Vec<String>,
+    /// Paths of props the client merges instead of replacing (v2
+    /// only); see [Inertia::merge](crate::Inertia::merge).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub merge_props: Vec<String>,
+}
+
+impl Page {
+    #[cfg(test)]
+    pub(crate) fn test_page() -> Page {
+        Page {
+            component: "Testing".to_string(),
+            props: serde_json::json!({ "test": "test" }),
+            url: "/test".to_string(),
+            version: None,
+            encrypt_history: false,
+            clear_history: false,
+            prefetch: Vec::new(),
+            merge_props: Vec::new(),
+        }
+    }
 }
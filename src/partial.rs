@@ -1,3 +1,5 @@
+use serde_json::{Map, Value};
+
 /// Partial reload data.
 ///
 /// Clients can request a subset of the props if a page component is
@@ -9,3 +11,207 @@ pub struct Partial {
     pub props: Vec<String>,
     pub component: String,
 }
+
+/// Partial reload headers of a request.
+///
+/// More info at: https://inertiajs.com/partial-reloads
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PartialReload {
+    /// `X-Inertia-Partial-Component`
+    pub(crate) component: Option<String>,
+    /// `X-Inertia-Partial-Data`
+    pub(crate) data: Option<Vec<String>>,
+    /// `X-Inertia-Partial-Except`
+    pub(crate) except: Option<Vec<String>>,
+    /// `X-Inertia-Reset`
+    pub(crate) reset: Vec<String>,
+}
+
+/// Filters `props` for a partial reload.
+///
+/// Only applies when the client is reloading the rendered component:
+/// then just the props in `partial_data` are kept (if given), minus
+/// those in `partial_except`. Otherwise all props are returned.
+///
+/// `partial_data` may contain dotted paths like `user.settings` to
+/// select nested values; the object structure around them is kept.
+pub(crate) fn resolve_props(
+    mut props: Map<String, Value>,
+    partial_data: Option<&[String]>,
+    partial_except: Option<&[String]>,
+    partial_component: Option<&str>,
+    rendered_component: &str,
+) -> Map<String, Value> {
+    if partial_component != Some(rendered_component) {
+        return props;
+    }
+    if let Some(data) = partial_data {
+        let paths: Vec<&str> = data.iter().map(String::as_str).collect();
+        props = select_paths(props, &paths);
+    }
+    if let Some(except) = partial_except {
+        props.retain(|key, _| !except.contains(key));
+    }
+    props
+}
+
+/// Keeps the values at the given dotted `paths`. A path selecting a
+/// parent keeps the whole parent, even if its children are requested
+/// too.
+fn select_paths(props: Map<String, Value>, paths: &[&str]) -> Map<String, Value> {
+    props
+        .into_iter()
+        .filter_map(|(key, value)| {
+            if paths.contains(&key.as_str()) {
+                return Some((key, value));
+            }
+            let children: Vec<&str> = paths
+                .iter()
+                .filter_map(|path| path.strip_prefix(key.as_str())?.strip_prefix('.'))
+                .collect();
+            match value {
+                Value::Object(map) if !children.is_empty() => {
+                    Some((key, Value::Object(select_paths(map, &children))))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The merge prop paths to announce to the client, leaving out those
+/// whose props were filtered from `props` and those the client asked
+/// to `reset`.
+pub(crate) fn resolve_merge_props(
+    merge_props: Vec<String>,
+    props: &Map<String, Value>,
+    reset: &[String],
+) -> Vec<String> {
+    merge_props
+        .into_iter()
+        .filter(|path| {
+            let key = path.split('.').next().unwrap_or_default();
+            props.contains_key(key) && !reset.contains(&key.to_string()) && !reset.contains(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn all_props() -> Map<String, Value> {
+        let Value::Object(props) = json!({ "users": [1, 2], "tags": ["new"], "count": 2 }) else {
+            unreachable!()
+        };
+        props
+    }
+
+    /// The sorted keys, whatever the map's order (see the
+    /// `preserve_order` feature).
+    fn keys(props: &Map<String, Value>) -> Vec<&str> {
+        let mut keys: Vec<&str> = props.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn it_keeps_only_partial_data() {
+        let data = strings(&["users", "count"]);
+
+        let props = resolve_props(all_props(), Some(&data), None, Some("Users"), "Users");
+
+        assert_eq!(keys(&props), vec!["count", "users"]);
+    }
+
+    #[test]
+    fn it_keeps_nested_partial_data() {
+        let Value::Object(props) = json!({
+            "user": { "name": "ada", "settings": { "theme": "dark" }, "posts": [1] },
+            "count": 2,
+        }) else {
+            unreachable!()
+        };
+        let data = strings(&["user.settings", "user.name.first"]);
+
+        let props = resolve_props(props, Some(&data), None, Some("Users"), "Users");
+
+        assert_eq!(
+            Value::Object(props),
+            json!({ "user": { "settings": { "theme": "dark" } } })
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_parent_of_overlapping_partial_data() {
+        let Value::Object(props) = json!({
+            "user": { "name": "ada", "settings": { "theme": "dark" } },
+            "count": 2,
+        }) else {
+            unreachable!()
+        };
+        let data = strings(&["user.settings", "user"]);
+
+        let props = resolve_props(props, Some(&data), None, Some("Users"), "Users");
+
+        assert_eq!(
+            Value::Object(props),
+            json!({ "user": { "name": "ada", "settings": { "theme": "dark" } } })
+        );
+    }
+
+    #[test]
+    fn it_drops_partial_except() {
+        let except = strings(&["users"]);
+
+        let props = resolve_props(all_props(), None, Some(&except), Some("Users"), "Users");
+        assert_eq!(keys(&props), vec!["count", "tags"]);
+
+        let data = strings(&["users", "count"]);
+        let props = resolve_props(
+            all_props(),
+            Some(&data),
+            Some(&except),
+            Some("Users"),
+            "Users",
+        );
+        assert_eq!(keys(&props), vec!["count"]);
+    }
+
+    #[test]
+    fn it_ignores_partials_for_other_components() {
+        let data = strings(&["users"]);
+
+        let props = resolve_props(all_props(), Some(&data), None, Some("Posts"), "Users");
+        assert_eq!(keys(&props), vec!["count", "tags", "users"]);
+
+        let props = resolve_props(all_props(), Some(&data), None, None, "Users");
+        assert_eq!(keys(&props), vec!["count", "tags", "users"]);
+    }
+
+    #[test]
+    fn it_leaves_out_reset_and_filtered_merge_props() {
+        let props = resolve_props(
+            all_props(),
+            Some(&strings(&["users", "tags"])),
+            None,
+            Some("Users"),
+            "Users",
+        );
+        let merge_props = strings(&["users.data", "tags", "count"]);
+
+        assert_eq!(
+            resolve_merge_props(merge_props.clone(), &props, &[]),
+            vec!["users.data", "tags"]
+        );
+        assert_eq!(
+            resolve_merge_props(merge_props, &props, &strings(&["users"])),
+            vec!["tags"]
+        );
+    }
+}
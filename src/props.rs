@@ -1,16 +1,23 @@
 //! The [Props] trait describes objects that can be used as Inertia
 //! props, and allows for handling around [inertia partial
 //! reloads](partial-reloads). See the trait documentation for more.
+//! [IntoInertiaProps] checks that they serialize to a json object.
 //!
 //! [partial-reloads]: https://inertiajs.com/the-protocol#partial-reloads
 
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{Map, Value};
 use std::error::Error;
+use std::fmt;
 
 use crate::partial::Partial;
+use crate::response::json_type_name;
 
 /// Objects that can be used as Inertia props.
+///
+/// Inertia expects props to be a json object, so implementations
+/// (like `#[derive(Serialize)]` structs or maps) should serialize to
+/// one. Anything else is rejected when rendering.
 pub trait Props {
     /// Serialize to json, given data about partial reloads.
     ///
@@ -34,3 +41,354 @@ where
         serde_json::to_value(self)
     }
 }
+
+/// Props that can be rendered by
+/// [Inertia::render](crate::Inertia::render).
+///
+/// Implemented for every [Props] type, and so for every
+/// [Serialize] type like a `#[derive(Serialize)]`
+/// struct per page. Inertia props must be a json object: other
+/// values, like a top-level array, are rejected with a
+/// [PropsError::NotAnObject] naming what was found.
+pub trait IntoInertiaProps {
+    /// Serializes the props, given data about partial reloads.
+    fn into_inertia_props(
+        self,
+        partial: Option<&Partial>,
+    ) -> Result<Map<String, Value>, PropsError>;
+}
+
+impl<T: Props> IntoInertiaProps for T {
+    fn into_inertia_props(
+        self,
+        partial: Option<&Partial>,
+    ) -> Result<Map<String, Value>, PropsError> {
+        match self.serialize(partial) {
+            Ok(Value::Object(props)) => Ok(props),
+            Ok(props) => Err(PropsError::NotAnObject(json_type_name(&props))),
+            Err(err) => Err(PropsError::Serialize(err.to_string())),
+        }
+    }
+}
+
+/// Why [IntoInertiaProps::into_inertia_props] failed.
+#[derive(Debug, PartialEq)]
+pub enum PropsError {
+    /// The props failed to serialize, with the serializer's message.
+    Serialize(String),
+    /// The props serialized to something other than a json object,
+    /// like `an array`.
+    NotAnObject(&'static str),
+}
+
+impl fmt::Display for PropsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Serialize(message) => write!(f, "{}", message),
+            Self::NotAnObject(found) => {
+                write!(f, "props must serialize to a json object, found {}", found)
+            }
+        }
+    }
+}
+
+impl Error for PropsError {}
+
+/// Props assembled from `(key, value)` pairs, see
+/// [Inertia::render_props](crate::Inertia::render_props).
+///
+/// The first value that fails to serialize fails the whole object,
+/// naming its key.
+pub(crate) struct PropPairs(Result<Map<String, Value>, serde_json::Error>);
+
+impl PropPairs {
+    pub(crate) fn new<K, V, I>(pairs: I) -> PropPairs
+    where
+        K: Into<String>,
+        V: Serialize,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let props = pairs
+            .into_iter()
+            .map(|(key, value)| {
+                let key = key.into();
+                match serde_json::to_value(value) {
+                    Ok(value) => Ok((key, value)),
+                    Err(err) => Err(serde::ser::Error::custom(format!("prop {key}: {err}"))),
+                }
+            })
+            .collect();
+        PropPairs(props)
+    }
+}
+
+impl Props for PropPairs {
+    fn serialize(self, _: Option<&Partial>) -> Result<Value, impl Error> {
+        self.0.map(Value::Object)
+    }
+}
+
+/// The base page's props with a modal's component and props added as
+/// the `modal` prop, see
+/// [Inertia::render_modal](crate::Inertia::render_modal).
+pub(crate) struct ModalProps<B, S> {
+    pub(crate) base: B,
+    pub(crate) component: String,
+    pub(crate) props: S,
+}
+
+impl<B: Props, S: Props> Props for ModalProps<B, S> {
+    fn serialize(self, partial: Option<&Partial>) -> Result<Value, impl Error> {
+        use serde::ser::Error as _;
+
+        let base = self
+            .base
+            .serialize(partial)
+            .map_err(serde_json::Error::custom)?;
+        let Value::Object(mut base) = base else {
+            return Err(serde_json::Error::custom(
+                "base page props must serialize to a json object",
+            ));
+        };
+        // Partial reloads select the page's props, not the modal's.
+        let props = self
+            .props
+            .serialize(None)
+            .map_err(serde_json::Error::custom)?;
+        base.insert(
+            "modal".to_string(),
+            serde_json::json!({ "component": self.component, "props": props }),
+        );
+        Ok::<_, serde_json::Error>(Value::Object(base))
+    }
+}
+
+/// Largest integer javascript numbers represent exactly
+/// (`Number.MAX_SAFE_INTEGER`).
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Replaces integers outside of javascript's safe range with their
+/// string representation, recursively.
+pub(crate) fn stringify_unsafe_integers(value: &mut Value) {
+    match value {
+        Value::Number(number) => {
+            let unsafe_integer = match (number.as_u64(), number.as_i64()) {
+                (Some(n), _) => n > MAX_SAFE_INTEGER,
+                (None, Some(n)) => n.unsigned_abs() > MAX_SAFE_INTEGER,
+                (None, None) => false,
+            };
+            if unsafe_integer {
+                *value = Value::String(number.to_string());
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(stringify_unsafe_integers),
+        Value::Object(map) => map.values_mut().for_each(stringify_unsafe_integers),
+        _ => {}
+    }
+}
+
+/// Sorts the keys of every object in `value` alphabetically,
+/// recursively.
+pub(crate) fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Array(values) => values.iter_mut().for_each(sort_keys),
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = std::mem::take(map).into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (key, mut value) in entries {
+                sort_keys(&mut value);
+                map.insert(key, value);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Adds the `shared` props to `props`, keeping the values already in
+/// `props`. With `deep`, objects present in both are merged
+/// recursively instead.
+///
+/// `props` is moved rather than copied; only the shared values that
+/// aren't overridden are cloned.
+pub(crate) fn merge_shared(
+    mut props: Map<String, Value>,
+    shared: &Map<String, Value>,
+    deep: bool,
+) -> Map<String, Value> {
+    for (key, value) in shared {
+        match (props.get_mut(key), value) {
+            (None, _) => {
+                props.insert(key.clone(), value.clone());
+            }
+            (Some(Value::Object(own)), Value::Object(shared)) if deep => {
+                let own = std::mem::take(own);
+                props.insert(key.clone(), Value::Object(merge_shared(own, shared, deep)));
+            }
+            (Some(_), _) => {}
+        }
+    }
+    props
+}
+
+/// Sets the value at a dotted `path` (like `users.data`) in
+/// `props`, replacing non-object values along the way with objects.
+pub(crate) fn insert_at_path(props: &mut Map<String, Value>, path: &str, value: Value) {
+    match path.split_once('.') {
+        None => {
+            props.insert(path.to_string(), value);
+        }
+        Some((key, rest)) => {
+            let child = props
+                .entry(key)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !child.is_object() {
+                *child = Value::Object(Map::new());
+            }
+            if let Value::Object(child) = child {
+                insert_at_path(child, rest, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_names_the_key_of_prop_pairs_that_fail_to_serialize() {
+        use std::collections::HashMap;
+
+        let props = PropPairs::new(vec![("count", json!(2)), ("title", json!("Posts"))]);
+        assert_eq!(
+            props.serialize(None).unwrap(),
+            json!({ "count": 2, "title": "Posts" })
+        );
+
+        let non_string_keys = HashMap::from([(vec![1u8], 1)]);
+        let props = PropPairs::new([("lookup", non_string_keys)]);
+        let err = props.serialize(None).unwrap_err().to_string();
+        assert!(err.starts_with("prop lookup: "), "{err}");
+    }
+
+    #[test]
+    fn it_only_accepts_props_serializing_to_an_object() {
+        #[derive(Serialize)]
+        struct PageProps {
+            posts: Vec<&'static str>,
+        }
+
+        let props = PageProps {
+            posts: vec!["post one"],
+        };
+        assert_eq!(
+            Value::Object(props.into_inertia_props(None).unwrap()),
+            json!({ "posts": ["post one"] })
+        );
+
+        assert_eq!(
+            vec!["post one"].into_inertia_props(None),
+            Err(PropsError::NotAnObject("an array"))
+        );
+    }
+
+    #[test]
+    fn it_merges_shared_props() {
+        let Value::Object(props) = json!({ "user": "page", "posts": [1, 2] }) else {
+            unreachable!()
+        };
+        let Value::Object(shared) = json!({ "user": "shared", "app": "Blog" }) else {
+            unreachable!()
+        };
+
+        let props = merge_shared(props, &shared, false);
+
+        assert_eq!(
+            Value::Object(props),
+            json!({ "user": "page", "posts": [1, 2], "app": "Blog" })
+        );
+    }
+
+    #[test]
+    fn it_deep_merges_shared_props() {
+        let Value::Object(props) = json!({
+            "auth": { "permissions": ["edit"], "user": { "name": "page" } }
+        }) else {
+            unreachable!()
+        };
+        let Value::Object(shared) = json!({
+            "auth": { "user": { "name": "shared", "id": 1 } },
+            "app": "Blog",
+        }) else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            Value::Object(merge_shared(props.clone(), &shared, false)),
+            json!({ "auth": { "permissions": ["edit"], "user": { "name": "page" } }, "app": "Blog" })
+        );
+        assert_eq!(
+            Value::Object(merge_shared(props, &shared, true)),
+            json!({
+                "auth": { "permissions": ["edit"], "user": { "name": "page", "id": 1 } },
+                "app": "Blog",
+            })
+        );
+    }
+
+    #[test]
+    fn it_inserts_at_dotted_paths() {
+        let mut props = json!({ "users": { "page": 2 }, "count": 1 });
+        let map = props.as_object_mut().unwrap();
+
+        insert_at_path(map, "users.data", json!([1, 2]));
+        insert_at_path(map, "count.total", json!(3));
+        insert_at_path(map, "tags", json!([]));
+
+        assert_eq!(
+            props,
+            json!({
+                "users": { "page": 2, "data": [1, 2] },
+                "count": { "total": 3 },
+                "tags": [],
+            })
+        );
+    }
+
+    #[test]
+    fn it_sorts_keys() {
+        let mut props = json!({
+            "users": [{ "name": "ada", "id": 1 }],
+            "count": 1,
+            "meta": { "title": "Users", "page": 2 },
+        });
+
+        sort_keys(&mut props);
+
+        assert_eq!(
+            serde_json::to_string(&props).unwrap(),
+            r#"{"count":1,"meta":{"page":2,"title":"Users"},"users":[{"id":1,"name":"ada"}]}"#
+        );
+    }
+
+    #[test]
+    fn it_stringifies_unsafe_integers() {
+        let mut props = json!({
+            "id": 1_152_921_504_606_846_976u64,
+            "negative": -9_007_199_254_740_993i64,
+            "users": [{ "id": 9_007_199_254_740_991u64, "score": 1.5 }],
+        });
+
+        stringify_unsafe_integers(&mut props);
+
+        assert_eq!(
+            props,
+            json!({
+                "id": "1152921504606846976",
+                "negative": "-9007199254740993",
+                "users": [{ "id": 9_007_199_254_740_991u64, "score": 1.5 }],
+            })
+        );
+    }
+}
@@ -1,7 +1,11 @@
-use crate::partial::Partial;
+use crate::partial::{Partial, PartialReload};
 use async_trait::async_trait;
 use axum::extract::{FromRequestParts, OriginalUri};
-use http::{request::Parts, HeaderMap, HeaderValue, StatusCode};
+use http::{
+    header::{ACCEPT, REFERER},
+    request::Parts,
+    HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+};
 
 /// Inertia-related information in the request.
 ///
@@ -13,6 +17,16 @@ pub(crate) struct Request {
     /// When using nested services, the `url` will include the full path.
     pub(crate) url: String,
     pub(crate) partial: Option<Partial>,
+    pub(crate) partial_reload: PartialReload,
+    pub(crate) method: Method,
+    /// See [InertiaConfig::csrf_token](crate::InertiaConfig::csrf_token).
+    pub(crate) csrf_token: Option<String>,
+    /// The `Referer` header, for redirecting back.
+    pub(crate) referer: Option<String>,
+    /// Whether the `Accept` header ranks `application/json` above
+    /// `text/html`, see
+    /// [InertiaConfig::json_api](crate::InertiaConfig::json_api).
+    pub(crate) prefers_json: bool,
 }
 
 impl Request {
@@ -23,8 +37,23 @@ impl Request {
             version: None,
             url: "/foo/bar".to_string(),
             partial: None,
+            partial_reload: PartialReload::default(),
+            method: Method::GET,
+            csrf_token: None,
+            referer: None,
+            prefers_json: false,
         }
     }
+
+    /// The path and query of the `Referer`, dropping its origin so it
+    /// can't redirect to another site.
+    pub(crate) fn referer_path(&self) -> Option<String> {
+        let uri: http::Uri = self.referer.as_deref()?.parse().ok()?;
+        let path = uri.path_and_query()?.as_str();
+        // `//host` and `/\host` are protocol-relative urls to browsers.
+        let relative = path.starts_with('/') && !path.starts_with("//") && !path.starts_with("/\\");
+        relative.then(|| path.to_string())
+    }
 }
 
 #[async_trait]
@@ -34,14 +63,30 @@ where
 {
     type Rejection = (StatusCode, HeaderMap<HeaderValue>);
 
-    async fn from_request_parts(mut parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let original_uri = OriginalUri::from_request_parts(&mut parts, state)
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Request::from_parts(parts, &HeaderName::from_static("x-inertia")).await
+    }
+}
+
+impl Request {
+    /// Extracts the request, detecting Inertia visits by the
+    /// `inertia_header` (`X-Inertia` by default, see
+    /// [InertiaConfig::request_header_name](crate::InertiaConfig::request_header_name)).
+    pub(crate) async fn from_parts(
+        parts: &mut Parts,
+        inertia_header: &HeaderName,
+    ) -> Result<Request, (StatusCode, HeaderMap<HeaderValue>)> {
+        let original_uri = OriginalUri::from_request_parts(parts, &())
             .await
             .unwrap_or_else(|e| match e {});
-        let url = original_uri.0.path().to_string();
+        let url = original_uri
+            .0
+            .path_and_query()
+            .map_or_else(|| original_uri.0.path(), |p| p.as_str())
+            .to_string();
         let is_xhr = parts
             .headers
-            .get("X-Inertia")
+            .get(inertia_header)
             .map(|s| s.to_str().map(|s| s == "true"))
             .transpose()
             .map_err(|_err| (StatusCode::BAD_REQUEST, HeaderMap::new()))?
@@ -52,19 +97,19 @@ where
             .map(|s| s.to_str().map(|s| s.to_string()))
             .transpose()
             .map_err(|_err| (StatusCode::BAD_REQUEST, HeaderMap::new()))?;
-        let partial_data = parts
-            .headers
-            .get("X-Inertia-Partial-Data")
-            .map(|s| s.to_str().map(|s| s.to_string()))
-            .transpose()
-            .map(|s| s.map(|s| s.split(',').map(|s| s.to_owned()).collect::<Vec<_>>()))
-            .map_err(|_err| (StatusCode::BAD_REQUEST, HeaderMap::new()))?;
+        let partial_data = header_list(parts, "X-Inertia-Partial-Data")?;
         let partial_component = parts
             .headers
             .get("X-Inertia-Partial-Component")
             .map(|s| s.to_str().map(|s| s.to_string()))
             .transpose()
             .map_err(|_err| (StatusCode::BAD_REQUEST, HeaderMap::new()))?;
+        let partial_reload = PartialReload {
+            component: partial_component.clone(),
+            data: partial_data.clone(),
+            except: header_list(parts, "X-Inertia-Partial-Except")?,
+            reset: header_list(parts, "X-Inertia-Reset")?.unwrap_or_default(),
+        };
         // TODO: trace warning if we have one of data/component without the other
         // TODO: should this enforce is_xhr is true?
         let partial = match (partial_data, partial_component) {
@@ -77,10 +122,68 @@ where
             version,
             url,
             partial,
+            partial_reload,
+            method: parts.method.clone(),
+            csrf_token: None,
+            referer: parts
+                .headers
+                .get(REFERER)
+                .and_then(|referer| referer.to_str().ok())
+                .map(str::to_string),
+            prefers_json: quality(&parts.headers, "application/json")
+                > quality(&parts.headers, "text/html"),
         })
     }
 }
 
+/// The `Accept` quality of `media_type`: the q-value of the most
+/// specific media range matching it, or 0 if none does.
+fn quality(headers: &HeaderMap, media_type: &str) -> f32 {
+    let (kind, _) = media_type.split_once('/').unwrap_or_default();
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|accept| accept.to_str().ok())
+        .flat_map(|accept| accept.split(','))
+        .filter_map(|media_range| {
+            let mut params = media_range.split(';');
+            let range = params.next().unwrap_or_default().trim();
+            let specificity = if range.eq_ignore_ascii_case(media_type) {
+                2
+            } else if range.eq_ignore_ascii_case(&format!("{kind}/*")) {
+                1
+            } else if range == "*/*" {
+                0
+            } else {
+                return None;
+            };
+            let q = params
+                .filter_map(|param| param.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+                .and_then(|(_, q)| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((specificity, q))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))
+        .map_or(0.0, |(_, q)| q)
+}
+
+/// Parses a comma separated header, like `X-Inertia-Partial-Data`.
+fn header_list(
+    parts: &Parts,
+    name: &str,
+) -> Result<Option<Vec<String>>, (StatusCode, HeaderMap<HeaderValue>)> {
+    parts
+        .headers
+        .get(name)
+        .map(|s| {
+            s.to_str()
+                .map(|s| s.split(',').map(|s| s.to_owned()).collect())
+        })
+        .transpose()
+        .map_err(|_err| (StatusCode::BAD_REQUEST, HeaderMap::new()))
+}
+
 #[cfg(test)]
 mod tests {
     use std::net::SocketAddr;
@@ -250,6 +353,41 @@ mod tests {
         assert_eq!(res.status(), StatusCode::OK);
     }
 
+    #[test]
+    fn it_ranks_accepted_media_types_by_quality() {
+        let prefers_json = |accept: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT, accept.parse().unwrap());
+            quality(&headers, "application/json") > quality(&headers, "text/html")
+        };
+
+        assert!(prefers_json("application/json"));
+        assert!(prefers_json("application/json, */*;q=0.1"));
+        assert!(prefers_json("text/html;q=0.5, application/*"));
+        assert!(!prefers_json("application/json;q=0.9, text/html"));
+        assert!(!prefers_json("text/html, application/json"));
+        assert!(!prefers_json("*/*"));
+        assert!(!prefers_json("application/json;q=0, */*;q=0.5"));
+    }
+
+    #[tokio::test]
+    async fn it_extracts_urls_with_query_strings() {
+        async fn handler(req: Request) {
+            assert_eq!(req.url, "/test?page=2".to_string());
+        }
+        let app = Router::new().route("/test", get(handler));
+        let (_, addr) = spawn_test_app(app).await;
+
+        let client = reqwest::Client::new();
+
+        let res = client
+            .get(format!("http://{}/test?page=2", &addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn it_extracts_original_urls_for_nested_routers() {
         async fn handler(req: Request) {
@@ -1,7 +1,11 @@
-use crate::config::InertiaConfig;
+use crate::config::{insert_into_head, InertiaConfig, LayoutError};
 use crate::{page::Page, request::Request};
+use axum::body::HttpBody;
 use axum::response::{Html, IntoResponse, Json};
-use http::HeaderMap;
+use http::{header::VARY, HeaderMap, HeaderValue, StatusCode};
+use maud::{html, DOCTYPE};
+use serde_json::{Map, Value};
+use std::backtrace::BacktraceStatus;
 
 /// An Inertia response.
 ///
@@ -9,26 +13,359 @@ use http::HeaderMap;
 /// https://inertiajs.com/the-protocol#inertia-responses
 pub struct Response {
     pub(crate) request: Request,
-    pub(crate) page: Page,
+    pub(crate) page: Result<Page, RenderError>,
     pub(crate) config: InertiaConfig,
+    /// Extra headers set by the handler.
+    pub(crate) headers: HeaderMap,
+    /// Makes Inertia visits do a full page reload instead.
+    pub(crate) force_reload: bool,
+    /// The [added layout](InertiaConfig::add_layout) to render with,
+    /// if not the default.
+    pub(crate) layout: Option<String>,
+    /// Per-request [window data](crate::Inertia::window_data).
+    pub(crate) window_data: Map<String, Value>,
+}
+
+/// Everything besides the [Page] needed to finish an Inertia
+/// response, see [Inertia::into_page](crate::Inertia::into_page).
+pub struct ResponseContext {
+    pub(crate) request: Request,
+    pub(crate) config: InertiaConfig,
+    pub(crate) headers: HeaderMap,
+    pub(crate) force_reload: bool,
+    pub(crate) layout: Option<String>,
+    pub(crate) window_data: Map<String, Value>,
+}
+
+impl ResponseContext {
+    /// Builds the response for `page`.
+    pub fn render(self, page: Page) -> Response {
+        self.into_response_with(Ok(page))
+    }
+
+    pub(crate) fn into_response_with(self, page: Result<Page, RenderError>) -> Response {
+        Response {
+            request: self.request,
+            page,
+            config: self.config,
+            headers: self.headers,
+            force_reload: self.force_reload,
+            layout: self.layout,
+            window_data: self.window_data,
+        }
+    }
+}
+
+impl Response {
+    /// Renders onto an existing `response` instead of a fresh one.
+    ///
+    /// The status and body are replaced, rendered headers overwrite
+    /// those of the same name (except `Vary`, which is merged) and
+    /// everything else, like extensions set by middleware, is kept.
+    pub fn render_into(self, response: &mut axum::response::Response) {
+        let (mut parts, body) = self.into_response().into_parts();
+        let vary: Vec<HeaderValue> = parts.headers.get_all(VARY).iter().cloned().collect();
+        parts.headers.remove(VARY);
+        *response.status_mut() = parts.status;
+        response.headers_mut().extend(parts.headers);
+        for field in vary.iter().filter_map(|value| value.to_str().ok()) {
+            append_vary(response.headers_mut(), field);
+        }
+        response.extensions_mut().extend(parts.extensions);
+        *response.body_mut() = body;
+    }
+}
+
+/// Details about a rendered Inertia response, passed to
+/// [InertiaConfig::on_response].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ResponseMetrics {
+    /// The rendered component.
+    pub component: String,
+    /// Whether this was a partial reload of the component.
+    pub partial: bool,
+    /// The size of the response body in bytes.
+    pub body_size: u64,
+    pub status: StatusCode,
+    /// The serialized props, if enabled with
+    /// [InertiaConfig::audit_props].
+    pub props: Option<String>,
+    /// Whether the props were over
+    /// [InertiaConfig::max_props_bytes], e.g. to count the pages a
+    /// [PropsLimit::Warn](crate::config::PropsLimit::Warn) limit let
+    /// through.
+    pub props_too_large: bool,
 }
 
 impl IntoResponse for Response {
     fn into_response(self) -> axum::response::Response {
-        let mut headers = HeaderMap::new();
+        if self.config.response_hook().is_none() {
+            return self.render_response();
+        }
+        let component = match &self.page {
+            Ok(page) => page.component.clone(),
+            Err(err) => err.component().unwrap_or_default().to_string(),
+        };
+        let audits_props = self.config.audits_props();
+        let limit = self.config.props_limit().map(|(limit, _)| limit);
+        let (props, props_too_large) = match &self.page {
+            Ok(page) if audits_props || limit.is_some() => {
+                let json = serde_json::to_string(&page.props).ok();
+                let too_large =
+                    matches!((&json, limit), (Some(json), Some(limit)) if json.len() > limit);
+                (json.filter(|_| audits_props), too_large)
+            }
+            _ => (None, false),
+        };
+        let partial = self.request.is_xhr
+            && self.request.partial_reload.component.as_deref() == Some(component.as_str());
+        let config = self.config.clone();
+        let response = self.render_response();
+        let metrics = ResponseMetrics {
+            component,
+            partial,
+            body_size: response.body().size_hint().exact().unwrap_or_default(),
+            status: response.status(),
+            props,
+            props_too_large,
+        };
+        if let Some(hook) = config.response_hook() {
+            hook(&metrics);
+        }
+        response
+    }
+}
+
+impl Response {
+    fn render_response(self) -> axum::response::Response {
+        let page = match self.page {
+            Ok(page) => page,
+            Err(err) => return error_response(&self.config, err),
+        };
+        let mut headers = self.headers;
+        if self.force_reload && self.request.is_xhr {
+            headers.insert(
+                "X-Inertia-Location",
+                self.config.url_for(&self.request.url).parse().unwrap(),
+            );
+            return (StatusCode::CONFLICT, headers).into_response();
+        }
         if let Some(version) = &self.config.version() {
             headers.insert("X-Inertia-Version", version.parse().unwrap());
         }
+        let inertia_header = self.config.inertia_header().clone();
+        // Header names are case-insensitive, but `HeaderName` is always
+        // lowercase; keep the conventional spelling for the default.
+        match inertia_header.as_str() {
+            "x-inertia" => append_vary(&mut headers, "X-Inertia"),
+            custom => append_vary(&mut headers, custom),
+        }
+        if self.config.serves_json_api() {
+            append_vary(&mut headers, "Accept");
+        }
         if self.request.is_xhr {
-            headers.insert("X-Inertia", "true".parse().unwrap());
-            (headers, Json(self.page)).into_response()
+            headers.insert(inertia_header, "true".parse().unwrap());
+            (headers, Json(page)).into_response()
+        } else if self.request.prefers_json && self.config.serves_json_api() {
+            (headers, Json(page.props)).into_response()
         } else {
-            let html = (self.config.layout())(serde_json::to_string(&self.page).unwrap());
-            (headers, Html(html)).into_response()
+            headers.extend(self.config.html_headers().clone());
+            let page = if self.config.pretty_prints_page() {
+                serde_json::to_string_pretty(&page)
+            } else {
+                serde_json::to_string(&page)
+            };
+            match self
+                .config
+                .try_layout(page.unwrap(), self.layout.as_deref(), &self.window_data)
+            {
+                Ok(html) => {
+                    let html = match &self.request.csrf_token {
+                        Some(token) => insert_into_head(
+                            html,
+                            &maud::html! { meta name="csrf-token" content=(token); }.into_string(),
+                        ),
+                        None => html,
+                    };
+                    (headers, Html(html)).into_response()
+                }
+                Err(err) => error_response(&self.config, RenderError::Layout(err)),
+            }
+        }
+    }
+}
+
+/// Errors that prevent an Inertia page from being rendered.
+#[derive(Debug)]
+pub(crate) enum RenderError {
+    /// The props couldn't be serialized to json.
+    Props { component: String, message: String },
+    /// The props serialized to something other than a json object.
+    NotAnObject {
+        component: String,
+        found: &'static str,
+    },
+    /// The page uses a feature the client's protocol version doesn't
+    /// support.
+    RequiresV2 {
+        component: String,
+        feature: &'static str,
+    },
+    /// The serialized props are larger than
+    /// [InertiaConfig::max_props_bytes].
+    PropsTooLarge {
+        component: String,
+        size: usize,
+        limit: usize,
+    },
+    /// A [cache tag](crate::Inertia::cache_tag) can't be sent.
+    InvalidCacheTag { component: String, tag: String },
+    /// The layout function failed.
+    Layout(LayoutError),
+}
+
+impl RenderError {
+    fn component(&self) -> Option<&str> {
+        match self {
+            Self::Props { component, .. }
+            | Self::NotAnObject { component, .. }
+            | Self::RequiresV2 { component, .. }
+            | Self::PropsTooLarge { component, .. }
+            | Self::InvalidCacheTag { component, .. } => Some(component),
+            Self::Layout(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Props { component, message } => {
+                write!(
+                    f,
+                    "failed to serialize props for {}: {}",
+                    component, message
+                )
+            }
+            Self::NotAnObject { component, found } => write!(
+                f,
+                "props for {} must serialize to a json object, found {}",
+                component, found
+            ),
+            Self::RequiresV2 { component, feature } => write!(
+                f,
+                "{} for {} requires Inertia protocol v2, see InertiaConfig::protocol_version",
+                feature, component
+            ),
+            Self::PropsTooLarge {
+                component,
+                size,
+                limit,
+            } => write!(
+                f,
+                "props for {} are about {} bytes, over the limit of {} bytes",
+                component, size, limit
+            ),
+            Self::InvalidCacheTag { component, tag } => {
+                write!(f, "invalid cache tag {:?} for {}", tag, component)
+            }
+            Self::Layout(err) => write!(f, "failed to render layout: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Layout(err) => Some(&**err),
+            _ => None,
         }
     }
 }
 
+/// Logs `err` and responds with a `500`.
+///
+/// Configs built by [vite::Development](crate::vite::Development)
+/// show the error (and its sources) in the browser, similar to vite's
+/// own error overlay. Otherwise the body is deliberately generic so
+/// no details leak.
+fn error_response(config: &InertiaConfig, err: RenderError) -> axum::response::Response {
+    eprintln!("Failed to render Inertia response: {err}");
+    if !config.shows_error_overlay() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response();
+    }
+
+    let mut causes = Vec::new();
+    let mut source = std::error::Error::source(&err);
+    while let Some(cause) = source {
+        causes.push(cause.to_string());
+        source = cause.source();
+    }
+    let backtrace = std::backtrace::Backtrace::capture();
+    let overlay = html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                title { "Inertia render error" }
+                meta charset="utf-8";
+            }
+            body style="margin:0;background:#181818;color:#d8d8d8;font-family:monospace" {
+                div style="margin:40px auto;max-width:960px;padding:24px;border-top:4px solid #ff5555" {
+                    h1 style="color:#ff5555;font-size:16px" { "Failed to render Inertia response" }
+                    pre style="white-space:pre-wrap" { (err.to_string()) }
+                    @for cause in &causes {
+                        pre style="white-space:pre-wrap;color:#ffb86c" { "caused by: " (cause) }
+                    }
+                    @if backtrace.status() == BacktraceStatus::Captured {
+                        pre style="white-space:pre-wrap;color:#888" { (backtrace.to_string()) }
+                    }
+                }
+            }
+        }
+    };
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Html(overlay.into_string()),
+    )
+        .into_response()
+}
+
+/// Names the json type of `value`, for error messages.
+pub(crate) fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Adds `field` to the `Vary` header, keeping whatever is already there.
+///
+/// Existing `Vary` values are collapsed into a single header, and
+/// field names are deduplicated case-insensitively.
+pub(crate) fn append_vary(headers: &mut HeaderMap, field: &str) {
+    let mut fields: Vec<String> = Vec::new();
+    let existing = headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+    for name in existing.chain(std::iter::once(field)) {
+        if !fields.iter().any(|f| f.eq_ignore_ascii_case(name)) {
+            fields.push(name.to_string());
+        }
+    }
+    if let Ok(value) = HeaderValue::from_str(&fields.join(", ")) {
+        headers.insert(VARY, value);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use http_body_util::BodyExt;
@@ -42,12 +379,7 @@ mod tests {
             is_xhr: false,
             ..Request::test_request()
         };
-        let page = Page {
-            component: "Testing",
-            props: serde_json::json!({ "test": "test" }),
-            url: "/test".to_string(),
-            version: None,
-        };
+        let page = Ok(Page::test_page());
 
         let layout = |props| {
             formatdoc! {r#"
@@ -69,6 +401,10 @@ mod tests {
             request,
             page,
             config,
+            headers: HeaderMap::new(),
+            force_reload: false,
+            layout: None,
+            window_data: Map::new(),
         }
         .into_response();
         let body = response.into_body().collect().await.unwrap().to_bytes();
@@ -76,4 +412,102 @@ mod tests {
 
         assert!(body.contains(r#""props":{"test":"test"}"#));
     }
+
+    #[test]
+    fn test_html_headers_only_on_initial_page_load() {
+        let layout = Box::new(|props| format!(r#"<div id="app" data-page='{}'></div>"#, props));
+        let config = InertiaConfig::new(None, layout).html_header(
+            http::header::LINK,
+            "</main.js>; rel=modulepreload".parse().unwrap(),
+        );
+
+        let html = Response {
+            request: Request {
+                is_xhr: false,
+                ..Request::test_request()
+            },
+            page: Ok(Page::test_page()),
+            config: config.clone(),
+            headers: HeaderMap::new(),
+            force_reload: false,
+            layout: None,
+            window_data: Map::new(),
+        }
+        .into_response();
+        assert_eq!(
+            html.headers().get(http::header::LINK).unwrap(),
+            "</main.js>; rel=modulepreload"
+        );
+
+        let json = Response {
+            request: Request::test_request(),
+            page: Ok(Page::test_page()),
+            config,
+            headers: HeaderMap::new(),
+            force_reload: false,
+            layout: None,
+            window_data: Map::new(),
+        }
+        .into_response();
+        assert!(json.headers().get(http::header::LINK).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_render_into_existing_response() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct RequestId(u32);
+
+        let mut response = axum::response::Response::new(axum::body::Body::from("placeholder"));
+        response.extensions_mut().insert(RequestId(7));
+        response
+            .headers_mut()
+            .insert(VARY, "Accept-Language".parse().unwrap());
+        response
+            .headers_mut()
+            .insert("X-Request-Id", "7".parse().unwrap());
+
+        Response {
+            request: Request::test_request(),
+            page: Ok(Page::test_page()),
+            config: InertiaConfig::new(None, Box::new(|props| props)),
+            headers: HeaderMap::new(),
+            force_reload: false,
+            layout: None,
+            window_data: Map::new(),
+        }
+        .render_into(&mut response);
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.extensions().get(), Some(&RequestId(7)));
+        let headers = response.headers();
+        assert_eq!(headers.get("X-Request-Id").unwrap(), "7");
+        assert_eq!(headers.get("X-Inertia").unwrap(), "true");
+        assert_eq!(headers.get(VARY).unwrap(), "Accept-Language, X-Inertia");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8(body.into())
+            .unwrap()
+            .contains(r#""component":"Testing""#));
+    }
+
+    #[test]
+    fn test_append_vary_keeps_existing_fields() {
+        let mut headers = HeaderMap::new();
+        headers.insert(VARY, "Accept-Language".parse().unwrap());
+
+        append_vary(&mut headers, "X-Inertia");
+
+        assert_eq!(headers.get(VARY).unwrap(), "Accept-Language, X-Inertia");
+    }
+
+    #[test]
+    fn test_append_vary_deduplicates_case_insensitively() {
+        let mut headers = HeaderMap::new();
+        headers.append(VARY, "x-inertia".parse().unwrap());
+        headers.append(VARY, "Accept, accept".parse().unwrap());
+
+        append_vary(&mut headers, "X-Inertia");
+
+        assert_eq!(headers.get_all(VARY).iter().count(), 1);
+        assert_eq!(headers.get(VARY).unwrap(), "x-inertia, Accept");
+    }
 }
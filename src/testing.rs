@@ -0,0 +1,111 @@
+//! Helpers for inspecting Inertia responses without running a server.
+//!
+//! [render_to_string] runs the same extraction and rendering logic as
+//! a real handler, which makes it handy for snapshot or golden-file
+//! tests of the html and json your server produces:
+//!
+//! ```rust
+//! use axum_inertia::{testing, InertiaConfig};
+//! use serde_json::json;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let layout = Box::new(|props| format!(r#"<div id="app" data-page='{}'></div>"#, props));
+//! let config = InertiaConfig::new(Some("123".to_string()), layout);
+//!
+//! let request = http::Request::builder()
+//!     .uri("/posts")
+//!     .header("X-Inertia", "true")
+//!     .header("X-Inertia-Version", "123")
+//!     .body(())
+//!     .unwrap();
+//!
+//! let body = testing::render_to_string(&config, request, "Posts/Index", json!({ "posts": [] })).await;
+//! assert_eq!(
+//!     body,
+//!     r#"{"component":"Posts/Index","props":{"posts":[]},"url":"/posts","version":"123"}"#
+//! );
+//! # }
+//! ```
+use crate::{props::IntoInertiaProps, Inertia, InertiaConfig};
+use axum::{body::to_bytes, extract::FromRequestParts, response::IntoResponse};
+
+/// Renders `component` with `props` for the given simulated request
+/// and returns the response body.
+///
+/// The request's headers decide what is rendered: an initial page
+/// load returns the layout html, while `X-Inertia` visits return the
+/// page object json (filtered by any partial reload headers). A
+/// mismatching `X-Inertia-Version` produces the empty body of the
+/// `409 Conflict` response.
+pub async fn render_to_string<P: IntoInertiaProps>(
+    config: &InertiaConfig,
+    request: http::Request<()>,
+    component: &'static str,
+    props: P,
+) -> String {
+    let (mut parts, _) = request.into_parts();
+    let response = match Inertia::from_request_parts(&mut parts, config).await {
+        Ok(inertia) => inertia.render(component, props).into_response(),
+        Err(rejection) => rejection.into_response(),
+    };
+    let body = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read response body");
+    String::from_utf8(body.to_vec()).expect("response body is not utf-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> InertiaConfig {
+        let layout =
+            Box::new(|props| format!(r#"<html><body><div id="app" data-page='{}'></div>"#, props));
+        InertiaConfig::new(Some("123".to_string()), layout)
+    }
+
+    #[tokio::test]
+    async fn it_renders_the_initial_page_load() {
+        let request = http::Request::builder().uri("/test").body(()).unwrap();
+
+        let body = render_to_string(&config(), request, "Testing", json!({ "test": "test" })).await;
+
+        assert_eq!(
+            body,
+            r#"<html><body><div id="app" data-page='{"component":"Testing","props":{"test":"test"},"url":"/test","version":"123"}'></div>"#
+        );
+    }
+
+    #[tokio::test]
+    async fn it_renders_inertia_visits() {
+        let request = http::Request::builder()
+            .uri("/test")
+            .header("X-Inertia", "true")
+            .header("X-Inertia-Version", "123")
+            .body(())
+            .unwrap();
+
+        let body = render_to_string(&config(), request, "Testing", json!({ "test": "test" })).await;
+
+        assert_eq!(
+            body,
+            r#"{"component":"Testing","props":{"test":"test"},"url":"/test","version":"123"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn it_renders_nothing_on_version_mismatch() {
+        let request = http::Request::builder()
+            .uri("/test")
+            .header("X-Inertia", "true")
+            .header("X-Inertia-Version", "456")
+            .body(())
+            .unwrap();
+
+        let body = render_to_string(&config(), request, "Testing", json!({ "test": "test" })).await;
+
+        assert_eq!(body, "");
+    }
+}
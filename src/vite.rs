@@ -26,40 +26,126 @@
 //! };
 //! ```
 //!
+//! Building a [Development] config while `APP_ENV` is `production`
+//! logs a warning, since its pages would load scripts from the dev
+//! server; see [Development::production_guard].
+//!
 //! [vitejs]: https://vitejs.dev
-use crate::config::InertiaConfig;
+use crate::config::{normalize_base_path, InertiaConfig};
+use base64::{prelude::BASE64_STANDARD, Engine};
 use hex::encode;
+use http::{
+    header::{CONTENT_SECURITY_POLICY, LINK},
+    HeaderValue,
+};
 use maud::{html, PreEscaped};
 use serde::Deserialize;
 use sha1::{Digest, Sha1};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
 use tera::{Context as TeraContext, Tera};
 
 pub struct Development {
+    scheme: String,
+    host: String,
     port: u16,
     main: &'static str,
+    extra_mains: Vec<&'static str>,
     lang: &'static str,
     title: &'static str,
     react: bool,
+    react_preamble: Option<String>,
+    client_query: Vec<&'static str>,
+    root_id: &'static str,
     template_engine: Option<Tera>,
     layout_template: Option<String>,
+    context_prefix: Option<&'static str>,
+    csp: Option<Csp>,
+    head_html: String,
+    minimal_head: bool,
+    module_type: bool,
+    main_integrity: Option<String>,
+    doctype: Option<&'static str>,
+    production_guard: ProductionGuard,
+    env_var: &'static str,
 }
 
 impl Default for Development {
     fn default() -> Self {
         Development {
+            scheme: "http".to_string(),
+            host: "localhost".to_string(),
             port: 5173,
             main: "src/main.ts",
+            extra_mains: Vec::new(),
             lang: "en",
-            title: "Vite",
+            title: "Inertia App",
             react: false,
+            react_preamble: None,
+            client_query: Vec::new(),
+            root_id: "app",
             template_engine: None,
             layout_template: None,
+            context_prefix: None,
+            csp: None,
+            head_html: String::new(),
+            minimal_head: false,
+            module_type: true,
+            main_integrity: None,
+            doctype: Some(HTML5_DOCTYPE),
+            production_guard: ProductionGuard::Warn,
+            env_var: "APP_ENV",
         }
     }
 }
 
 impl Development {
+    /// Configures the dev server location from a vite "hot" file.
+    ///
+    /// Plugins like `laravel-vite-plugin` write the dev server url
+    /// (e.g. `http://localhost:5173`) to this file while `vite dev` is
+    /// running. If the file is missing or doesn't contain a url, the
+    /// [default](Development::default) location is used.
+    pub fn from_hot_file<P: AsRef<Path>>(path: P) -> Self {
+        let development = Development::default();
+        match std::fs::read_to_string(path) {
+            Ok(contents) => development.hot_url(&contents),
+            Err(_) => development,
+        }
+    }
+
+    fn hot_url(mut self, url: &str) -> Self {
+        let Some((scheme, rest)) = url.trim().split_once("://") else {
+            return self;
+        };
+        let authority = rest.split('/').next().unwrap_or_default();
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => (host, port.parse().ok()),
+            _ => (authority, None),
+        };
+        if host.is_empty() {
+            return self;
+        }
+        self.port = port.unwrap_or(if scheme == "https" { 443 } else { 80 });
+        self.scheme = scheme.to_string();
+        self.host = host.to_string();
+        self
+    }
+
+    /// Sets the dev server host as the *browser* sees it.
+    ///
+    /// The server never connects to the dev server itself, it only
+    /// renders urls for the browser. So with Docker Compose, where the
+    /// backend runs in a container but the browser reaches vite through
+    /// the host machine, use the browser-facing address (like
+    /// `localhost`) even if the container can't reach it.
+    pub fn host<H: AsRef<str>>(mut self, host: H) -> Self {
+        self.host = host.as_ref().to_owned();
+        self
+    }
+
     pub fn port(mut self, port: u16) -> Self {
         self.port = port;
         self
@@ -70,11 +156,49 @@ impl Development {
         self
     }
 
+    /// Loads another entry script after [main](Self::main), e.g. a
+    /// separate worker entry. May be called multiple times; all scripts
+    /// share the one `@vite/client`.
+    pub fn add_main(mut self, main: &'static str) -> Self {
+        self.extra_mains.push(main);
+        self
+    }
+
+    /// Sets what [into_config](Self::into_config) does when the
+    /// [environment variable](Self::env_var) says `production`, i.e.
+    /// pages would load their scripts from a dev server on
+    /// `localhost`. Defaults to [ProductionGuard::Warn].
+    pub fn production_guard(mut self, production_guard: ProductionGuard) -> Self {
+        self.production_guard = production_guard;
+        self
+    }
+
+    /// Sets the environment variable the [production
+    /// guard](Self::production_guard) checks, `APP_ENV` by default.
+    pub fn env_var(mut self, env_var: &'static str) -> Self {
+        self.env_var = env_var;
+        self
+    }
+
     pub fn lang(mut self, lang: &'static str) -> Self {
         self.lang = lang;
         self
     }
 
+    /// Adds a Subresource Integrity hash (like `sha384-...`) to the
+    /// main script, e.g. when the dev server sits behind a proxy that
+    /// checks integrity. Off by default, since dev assets change all
+    /// the time.
+    pub fn main_integrity<I: Into<String>>(mut self, integrity: I) -> Self {
+        self.main_integrity = Some(integrity.into());
+        self
+    }
+
+    /// Sets the title of the default layout, `Inertia App` by default.
+    ///
+    /// A page's own title, taken from a prop with
+    /// [InertiaConfig::title_from_prop], replaces it; pages without
+    /// one show this title.
     pub fn title(mut self, title: &'static str) -> Self {
         self.title = title;
         self
@@ -89,6 +213,43 @@ impl Development {
         self
     }
 
+    /// Appends a query string (like `hmr=1&foo=bar`) to the
+    /// `@vite/client` url, for plugins that need one. May be called
+    /// multiple times.
+    pub fn client_query(mut self, query: &'static str) -> Self {
+        self.client_query.push(query);
+        self
+    }
+
+    /// Sets up react with a custom preamble instead of the one for
+    /// `@vitejs/plugin-react`, e.g. for forks using a different guard
+    /// variable.
+    ///
+    /// `{dev_server_url}` in `code` is replaced with the url of the
+    /// dev server.
+    pub fn react_preamble<C: AsRef<str>>(mut self, code: C) -> Self {
+        self.react = true;
+        self.react_preamble = Some(code.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the id of the element the app is mounted into (`app` by
+    /// default). Pass the same value as `id` to `createInertiaApp`.
+    pub fn root_id(mut self, root_id: &'static str) -> Self {
+        self.root_id = root_id;
+        self
+    }
+
+    /// Renders pages with the `layout_template` of `engine` instead of
+    /// the default layout.
+    ///
+    /// The context has the mount element as `application` (use `{{
+    /// application | safe }}`), and its parts as `root_id` and
+    /// `data_page`, the raw page json, for building it by hand. `lang`
+    /// holds the configured lang, for `<html lang="{{ lang }}">`.
+    /// `data_page` is only html-escaped by Tera's autoescaping, which
+    /// by default applies to `.html`, `.htm` and `.xml` templates;
+    /// other templates must use `{{ data_page | escape }}`.
     pub fn template_engine<T: AsRef<str>>(mut self, engine: Tera, layout_template: T) -> Self {
         self.template_engine = Some(engine);
         self.layout_template = Some(layout_template.as_ref().to_owned());
@@ -96,162 +257,600 @@ impl Development {
         self
     }
 
+    /// Nests the template variables (`application`, `vite_main`, ...)
+    /// under `prefix`, e.g. `{{ inertia.vite_main | safe }}`, so they
+    /// don't collide with the template's own variables.
+    pub fn template_context_prefix(mut self, prefix: &'static str) -> Self {
+        self.context_prefix = Some(prefix);
+        self
+    }
+
+    /// Appends raw html to the head of the default layout, e.g. meta
+    /// tags or a favicon link. May be called multiple times.
+    ///
+    /// Ignored when using a [template engine](Self::template_engine).
+    pub fn head_html<H: AsRef<str>>(mut self, html: H) -> Self {
+        self.head_html.push_str(html.as_ref());
+        self
+    }
+
+    /// Leaves the `title`, `charset` and `viewport` tags out of the
+    /// default layout, to supply them with [head_html](Self::head_html)
+    /// instead.
+    pub fn minimal_head(mut self) -> Self {
+        self.minimal_head = true;
+        self
+    }
+
+    /// Renders the main scripts as module scripts (`type="module"`,
+    /// the default) or, with `false`, as classic scripts.
+    ///
+    /// Vite's output needs module scripts; classic scripts are only
+    /// for unusual setups. The `@vite/client` script is always a
+    /// module script, since it uses `import`.
+    pub fn module_type(mut self, module: bool) -> Self {
+        self.module_type = module;
+        self
+    }
+
+    /// Sets the doctype of the default layout, `<!DOCTYPE html>` by
+    /// default, e.g. for XHTML. `None` leaves it out.
+    pub fn doctype(mut self, doctype: Option<&'static str>) -> Self {
+        self.doctype = doctype;
+        self
+    }
+
+    /// Adds a `Content-Security-Policy` header to initial page loads
+    /// that allows the vite dev server, including its websocket.
+    ///
+    /// See [csp_policy](Self::csp_policy) to send a policy of your own.
+    pub fn csp(mut self) -> Self {
+        self.csp = Some(Csp::Default);
+        self
+    }
+
+    /// Adds a `Content-Security-Policy` header with `policy` to
+    /// initial page loads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `policy` isn't a valid header value, e.g. because it
+    /// contains a newline.
+    pub fn csp_policy<P: AsRef<str>>(mut self, policy: P) -> Self {
+        self.csp = Some(Csp::Policy(valid_csp(policy.as_ref())));
+        self
+    }
+
+    /// The policy sent by [csp](Self::csp). Dev mode injects inline
+    /// scripts (the react preamble) and styles, so those are allowed.
+    fn default_csp(&self) -> String {
+        let origin = self.dev_server_url();
+        let ws_scheme = if self.scheme == "https" { "wss" } else { "ws" };
+        let ws = format!("{}://{}:{}", ws_scheme, self.host, self.port);
+        format!(
+            "default-src 'self'; script-src 'self' 'unsafe-inline' {origin}; \
+             style-src 'self' 'unsafe-inline' {origin}; img-src 'self' data: {origin}; \
+             font-src 'self' data: {origin}; connect-src 'self' {origin} {ws}"
+        )
+    }
+
+    /// Like [into_config](Self::into_config), but checks the layout
+    /// template first.
+    ///
+    /// The template must exist in the [template
+    /// engine](Self::template_engine). It is dry-rendered and must
+    /// include the mount element (i.e. use `{{ application | safe }}`,
+    /// or build an element with `id="{{ root_id }}"` and the
+    /// `data_page` in its `data-page` attribute), otherwise the page
+    /// would render without anywhere for the app to boot.
+    pub fn try_into_config(self) -> Result<InertiaConfig, ViteError> {
+        if let (Some(engine), Some(layout_template)) =
+            (&self.template_engine, &self.layout_template)
+        {
+            check_template_exists(engine, layout_template)?;
+        }
+        let layout_template = self.layout_template.clone();
+        let root_id = self.root_id;
+        let config = self.into_config();
+        if let Some(layout_template) = layout_template {
+            check_application_rendered(&config, layout_template, root_id)?;
+        }
+        Ok(config)
+    }
+
     pub fn into_config(self) -> InertiaConfig {
+        self.check_environment();
+        let csp = csp_header(&self.csp, || self.default_csp());
         let layout = Box::new(move |props| {
             if let Some(layout_template) = &self.layout_template {
                 let mut context = TeraContext::new();
 
-                let vite_client = html! { 
-                    script type="module" src=(format!("http://localhost:{}/@vite/client", self.port)) {}
-                }.into_string();
-                context.insert(
-                    "vite_client",
-                    &vite_client
-                );
-
-                let vite_main = html! {
-                    script type="module" src=(format!("http://localhost:{}/{}", self.port, self.main)) {}
-                }.into_string();
-                context.insert(
-                    "vite_main",
-                    &vite_main,
-                );
-
-                let react_preamble = html!{
+                let vite_src = self.vite_client_url();
+                let vite_client = html! {
+                    script type="module" src=(vite_src) {}
+                }
+                .into_string();
+                context.insert("vite_client", &vite_client);
+
+                context.insert("vite_main", &self.main_scripts());
+
+                let react_preamble = html! {
                     script type="module" { (PreEscaped(self.build_react_preamble())) }
-                }.into_string();
+                }
+                .into_string();
                 context.insert("vite_react_refresh", &react_preamble);
 
                 let app_element = html! {
-                    div #app data-page=(props) {}
+                    div id=(self.root_id) data-page=(props) {}
                 }
                 .into_string();
                 context.insert("application", &app_element);
+                context.insert("data_page", &props);
+                context.insert("root_id", self.root_id);
+                context.insert("lang", self.lang);
 
+                let context = prefixed_context(context, self.context_prefix);
                 match &self.template_engine {
-                    Some(template_engine) => {
-                        match template_engine.render(layout_template, &context) {
-                            Ok(output) => output,
-                            Err(err) => {
-                                eprintln!("Failed to render template {err}");
-                                "".to_string()
-                            }
-                        }
-                    }
-                    None => "".to_string(),
+                    Some(template_engine) => Ok(template_engine.render(layout_template, &context)?),
+                    None => Ok("".to_string()),
                 }
             } else {
-                let vite_src = format!("http://localhost:{}/@vite/client", self.port);
-                let main_src = format!("http://localhost:{}/{}", self.port, self.main);
+                let vite_src = self.vite_client_url();
                 let preamble_code = if self.react {
                     Some(PreEscaped(self.build_react_preamble()))
                 } else {
                     None
                 };
-                html! {
+                Ok(html! {
+                    (PreEscaped(self.doctype.unwrap_or_default()))
                     html lang=(self.lang) {
                         head {
-                            title { (self.title) }
-                            meta charset="utf-8";
-                            meta name="viewport" content="width=device-width, initial-scale=1.0";
+                            @if !self.minimal_head {
+                                title { (self.title) }
+                                meta charset="utf-8";
+                                meta name="viewport" content="width=device-width, initial-scale=1.0";
+                            }
                             @if let Some(preamble_code) = preamble_code {
                                 script type="module" { (preamble_code) }
                             }
                             script type="module" src=(vite_src) {}
-                            script type="module" src=(main_src) {}
+                            (PreEscaped(self.main_scripts()))
+                            (PreEscaped(&self.head_html))
                         }
 
                         body {
-                            div #app data-page=(props) {}
+                            div id=(self.root_id) data-page=(props) {}
                         }
                     }
                 }
-                .into_string()
+                .into_string())
             }
         });
 
-        InertiaConfig::new(None, layout)
+        let config = InertiaConfig::new_fallible(None, layout)
+            .error_overlay()
+            .pretty_page_json(true);
+        match csp {
+            Some(csp) => config.html_header(CONTENT_SECURITY_POLICY, csp),
+            None => config,
+        }
+    }
+
+    fn script_type(&self) -> Option<&'static str> {
+        self.module_type.then_some("module")
+    }
+
+    fn check_environment(&self) {
+        let production = std::env::var(self.env_var)
+            .is_ok_and(|env| env.trim().eq_ignore_ascii_case("production"));
+        if !production {
+            return;
+        }
+        let message = format!(
+            "{}=production, but the Inertia config uses the vite dev server at {}",
+            self.env_var,
+            self.dev_server_url()
+        );
+        match self.production_guard {
+            ProductionGuard::Off => {}
+            ProductionGuard::Warn => eprintln!("Warning: {message}"),
+            ProductionGuard::Panic => panic!("{message}"),
+        }
+    }
+
+    /// The script tags for the main entry and any
+    /// [added](Self::add_main) ones.
+    fn main_scripts(&self) -> String {
+        let main_src = |main| format!("{}/{}", self.dev_server_url(), main);
+        html! {
+            script type=[self.script_type()] src=(main_src(self.main)) integrity=[&self.main_integrity] {}
+            @for main in &self.extra_mains {
+                script type=[self.script_type()] src=(main_src(main)) {}
+            }
+        }
+        .into_string()
+    }
+
+    fn dev_server_url(&self) -> String {
+        format!("{}://{}:{}", self.scheme, self.host, self.port)
     }
 
+    fn vite_client_url(&self) -> String {
+        let query: Vec<String> = self
+            .client_query
+            .iter()
+            .map(|query| encode_query(query.trim_start_matches(['?', '&'])))
+            .filter(|query| !query.is_empty())
+            .collect();
+        if query.is_empty() {
+            format!("{}/@vite/client", self.dev_server_url())
+        } else {
+            format!("{}/@vite/client?{}", self.dev_server_url(), query.join("&"))
+        }
+    }
+
+    /// The react-refresh preamble. The default one only sets up the
+    /// globals once, so injecting it again (e.g. with StrictMode and
+    /// fast refresh) doesn't clobber the registered components.
     fn build_react_preamble(&self) -> String {
+        if let Some(preamble) = &self.react_preamble {
+            return preamble.replace("{dev_server_url}", &self.dev_server_url());
+        }
         format!(
             r#"
-import RefreshRuntime from "http://localhost:{}/@react-refresh"
-RefreshRuntime.injectIntoGlobalHook(window)
-window.$RefreshReg$ = () => {{}}
-window.$RefreshSig$ = () => (type) => type
-window.__vite_plugin_react_preamble_installed__ = true
+import RefreshRuntime from "{}/@react-refresh"
+if (!window.__vite_plugin_react_preamble_installed__) {{
+  RefreshRuntime.injectIntoGlobalHook(window)
+  window.$RefreshReg$ = () => {{}}
+  window.$RefreshSig$ = () => (type) => type
+  window.__vite_plugin_react_preamble_installed__ = true
+}}
 "#,
-            self.port
+            self.dev_server_url()
         )
     }
 }
 
+/// What to do when a [Development] config is built in production, see
+/// [Development::production_guard].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProductionGuard {
+    /// Don't check the environment.
+    Off,
+    /// Log a warning.
+    #[default]
+    Warn,
+    /// Panic, so the misconfigured server doesn't start.
+    Panic,
+}
+
 pub struct Production {
     main: ManifestEntry,
-    css: Option<String>,
     title: &'static str,
     lang: &'static str,
-    /// SHA1 hash of the contents of the manifest file.
+    root_id: &'static str,
+    /// SHA1 hash of the contents of the manifest file, unless read
+    /// from a [version file](Self::with_version_file).
     version: String,
     template_engine: Option<Tera>,
     layout_template: Option<String>,
+    context_prefix: Option<&'static str>,
     asset_path: Option<String>,
+    base_path: String,
+    manifest: HashMap<String, ManifestEntry>,
+    fonts: Vec<String>,
+    link_header: bool,
+    output_dir: Option<PathBuf>,
+    inline_css_below: Option<usize>,
+    csp: Option<Csp>,
+    head_html: String,
+    minimal_head: bool,
+    crossorigin: Option<&'static str>,
+    referrer_policy: Option<&'static str>,
+    base_tag: bool,
+    css_media: Vec<CssMedia>,
+    script_loading: Option<ScriptLoading>,
+    module_type: bool,
+    doctype: Option<&'static str>,
+    asset_url_rewriter: Option<AssetUrlRewriter>,
+    /// The directory of the manifest, if it was read from a file.
+    manifest_dir: Option<PathBuf>,
+    /// When the manifest file was last modified, if it was read from
+    /// a file.
+    manifest_modified: Option<SystemTime>,
+    public_root: Option<PathBuf>,
+    build_info: Option<String>,
+}
+
+/// How the main script is loaded, see [Production::script_defer].
+#[derive(Clone, Copy, PartialEq)]
+enum ScriptLoading {
+    Defer,
+    Async,
 }
 
+/// A `media` attribute for the stylesheets matching the predicate, see
+/// [Production::css_media].
+type CssMedia = (Box<dyn Fn(&str) -> bool + Send + Sync>, &'static str);
+
+/// See [Production::asset_url_rewriter].
+type AssetUrlRewriter = Box<dyn Fn(&str) -> String + Send + Sync>;
+
 impl Production {
+    /// Loads the vite manifest at `manifest_path` and uses its `main`
+    /// entry.
+    ///
+    /// `main` may contain `*` wildcards, like `*/main.ts`, for when the
+    /// entry key varies. Exactly one entry must match.
     pub fn new(
         manifest_path: &'static str,
         main: &'static str,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let bytes = std::fs::read(manifest_path)?;
+        let bytes = std::fs::read(manifest_path).map_err(ViteError::ManifestMissing)?;
+
+        Ok(Self::new_from_string(&String::from_utf8(bytes)?, main)?.loaded_from(manifest_path))
+    }
+
+    /// Like [new](Self::new), but reads the manifest without blocking
+    /// the async runtime. Requires the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub async fn new_async(
+        manifest_path: &'static str,
+        main: &'static str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let bytes = tokio::fs::read(manifest_path)
+            .await
+            .map_err(ViteError::ManifestMissing)?;
+
+        Ok(Self::new_from_string(&String::from_utf8(bytes)?, main)?.loaded_from(manifest_path))
+    }
+
+    /// Like [new](Self::new), but takes the manifest contents, e.g.
+    /// from an embedded file (`include_bytes!` or `rust-embed`).
+    ///
+    /// The version is the hash of `bytes`.
+    pub fn from_bytes(
+        bytes: &[u8],
+        main: &'static str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::new_from_string(std::str::from_utf8(bytes)?, main)
+    }
+
+    /// Like [from_bytes](Self::from_bytes), but reads the manifest
+    /// from any byte source.
+    pub fn from_reader<R: std::io::Read>(
+        mut reader: R,
+        main: &'static str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(ViteError::ManifestMissing)?;
+
+        Self::from_bytes(&bytes, main)
+    }
+
+    /// Like [new](Self::new), but fails on manifest entries with
+    /// fields vite doesn't write, to catch schema changes early.
+    pub fn new_strict(
+        manifest_path: &'static str,
+        main: &'static str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let bytes = std::fs::read(manifest_path).map_err(ViteError::ManifestMissing)?;
+
+        Ok(Self::from_manifest(&String::from_utf8(bytes)?, main, true)?.loaded_from(manifest_path))
+    }
 
-        Self::new_from_string(&String::from_utf8(bytes)?, main)
+    fn loaded_from(mut self, manifest_path: &str) -> Self {
+        self.manifest_dir = Path::new(manifest_path).parent().map(Path::to_path_buf);
+        self.manifest_modified = std::fs::metadata(manifest_path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        self
     }
 
     fn new_from_string(
         manifest_string: &str,
         main: &'static str,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut manifest: HashMap<String, ManifestEntry> = serde_json::from_str(&manifest_string)?;
-        let entry = manifest.remove(main).ok_or(ViteError::EntryMissing(main))?;
+        Self::from_manifest(manifest_string, main, false)
+    }
+
+    fn from_manifest(
+        manifest_string: &str,
+        main: &'static str,
+        strict: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if manifest_string.trim().is_empty() {
+            return Err(ViteError::EmptyManifest.into());
+        }
+        // Parse the entries one by one, so errors can name the entry.
+        #[cfg(not(feature = "json5"))]
+        let entries: HashMap<String, serde_json::Value> = serde_json::from_str(manifest_string)?;
+        #[cfg(feature = "json5")]
+        let entries: HashMap<String, serde_json::Value> = json5::from_str(manifest_string)?;
+        let manifest = entries
+            .into_iter()
+            .map(|(name, entry)| {
+                let strict_check = if strict {
+                    StrictManifestEntry::deserialize(&entry).map(|_| ())
+                } else {
+                    Ok(())
+                };
+                match strict_check.and_then(|()| serde_json::from_value(entry)) {
+                    Ok(entry) => Ok((name, entry)),
+                    Err(err) => Err(ViteError::MalformedEntry {
+                        name,
+                        detail: err.to_string(),
+                    }),
+                }
+            })
+            .collect::<Result<HashMap<String, ManifestEntry>, ViteError>>()?;
+        Self::from_entries(manifest_string, manifest, main)
+    }
+
+    /// Like [from_bytes](Self::from_bytes), but for a build
+    /// manifest in another `format`, like webpack's
+    /// [entrypoints.json](Entrypoints).
+    ///
+    /// The version is the hash of `manifest_string`.
+    pub fn from_format<F: ManifestFormat>(
+        format: F,
+        manifest_string: &str,
+        main: &'static str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if manifest_string.trim().is_empty() {
+            return Err(ViteError::EmptyManifest.into());
+        }
+        let manifest = format
+            .entrypoints(manifest_string)?
+            .into_iter()
+            .map(|(name, entrypoint)| {
+                let mut scripts = entrypoint.js;
+                let Some(file) = scripts.pop() else {
+                    return Err(ViteError::MalformedEntry {
+                        name,
+                        detail: "entrypoint has no js files".to_string(),
+                    });
+                };
+                let entry = ManifestEntry {
+                    file,
+                    integrity: None,
+                    css: Some(entrypoint.css),
+                    imports: Vec::new(),
+                    is_entry: Some(true),
+                    scripts,
+                };
+                Ok((name, entry))
+            })
+            .collect::<Result<HashMap<String, ManifestEntry>, ViteError>>()?;
+        Self::from_entries(manifest_string, manifest, main)
+    }
+
+    fn from_entries(
+        manifest_string: &str,
+        manifest: HashMap<String, ManifestEntry>,
+        main: &'static str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let entry = find_entry(&manifest, main)?.clone();
         let mut hasher = Sha1::new();
         hasher.update(manifest_string.as_bytes());
         let result = hasher.finalize();
         let version = encode(result);
-        let css = {
-            if let Some(css_sources) = &entry.css {
-                let mut css = String::new();
-                for source in css_sources {
-                    css.push_str(&format!(r#"<link rel="stylesheet" href="/{source}"/>"#));
-                }
-                Some(css)
-            } else {
-                None
-            }
-        };
         Ok(Self {
             main: entry,
-            css,
-            title: "Vite",
+            title: "Inertia App",
             lang: "en",
+            root_id: "app",
             version,
             template_engine: None,
             layout_template: None,
+            context_prefix: None,
             asset_path: None,
+            base_path: String::new(),
+            manifest,
+            fonts: Vec::new(),
+            link_header: false,
+            output_dir: None,
+            inline_css_below: None,
+            csp: None,
+            head_html: String::new(),
+            minimal_head: false,
+            crossorigin: None,
+            referrer_policy: None,
+            base_tag: false,
+            css_media: Vec::new(),
+            script_loading: None,
+            module_type: true,
+            doctype: Some(HTML5_DOCTYPE),
+            asset_url_rewriter: None,
+            manifest_dir: None,
+            manifest_modified: None,
+            public_root: None,
+            build_info: None,
         })
     }
 
+    /// Checks that every `integrity` hash in the manifest looks like
+    /// `sha256-`, `sha384-` or `sha512-` followed by a base64 digest,
+    /// so a corrupted manifest fails at startup rather than having
+    /// browsers block the assets. Empty hashes are invalid too.
+    pub fn validate_integrity(self) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut names: Vec<&String> = self.manifest.keys().collect();
+        names.sort();
+        for name in names {
+            let valid = match &self.manifest[name].integrity {
+                // An empty value would still add an `integrity`
+                // attribute, which browsers reject.
+                Some(integrity) => {
+                    !integrity.trim().is_empty()
+                        && integrity.split_whitespace().all(valid_integrity)
+                }
+                None => true,
+            };
+            if !valid {
+                return Err(ViteError::InvalidIntegrity {
+                    entry: name.clone(),
+                }
+                .into());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Reads the asset version from a sidecar file (e.g. a
+    /// `version.txt` written by CI) instead of hashing the manifest.
+    ///
+    /// Useful when several services must agree on the version. If the
+    /// file can't be read or is empty, the SHA1 hash of the manifest
+    /// is kept.
+    pub fn with_version_file<P: AsRef<Path>>(self, path: P) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => self.with_version(&contents),
+            Err(_) => self,
+        }
+    }
+
+    fn with_version(mut self, contents: &str) -> Self {
+        let version = contents.trim();
+        if !version.is_empty() {
+            self.version = version.to_string();
+        }
+        self
+    }
+
     pub fn lang(mut self, lang: &'static str) -> Self {
         self.lang = lang;
         self
     }
 
+    /// Sets the title of the default layout, `Inertia App` by default.
+    ///
+    /// A page's own title, taken from a prop with
+    /// [InertiaConfig::title_from_prop], replaces it; pages without
+    /// one show this title.
     pub fn title(mut self, title: &'static str) -> Self {
         self.title = title;
         self
     }
 
+    /// Sets the id of the element the app is mounted into (`app` by
+    /// default). Pass the same value as `id` to `createInertiaApp`.
+    pub fn root_id(mut self, root_id: &'static str) -> Self {
+        self.root_id = root_id;
+        self
+    }
+
+    /// Renders pages with the `layout_template` of `engine` instead of
+    /// the default layout.
+    ///
+    /// The context has the mount element as `application` (use `{{
+    /// application | safe }}`), and its parts as `root_id` and
+    /// `data_page`, the raw page json, for building it by hand. `lang`
+    /// holds the configured lang, for `<html lang="{{ lang }}">`.
+    /// `data_page` is only html-escaped by Tera's autoescaping, which
+    /// by default applies to `.html`, `.htm` and `.xml` templates;
+    /// other templates must use `{{ data_page | escape }}`.
     pub fn template_engine<T: AsRef<str>>(mut self, engine: Tera, layout_template: T) -> Self {
         self.template_engine = Some(engine);
         self.layout_template = Some(layout_template.as_ref().to_owned());
@@ -259,203 +858,1910 @@ impl Production {
         self
     }
 
+    /// Nests the template variables (`application`, `vite_main`, ...)
+    /// under `prefix`, e.g. `{{ inertia.vite_main | safe }}`, so they
+    /// don't collide with the template's own variables.
+    pub fn template_context_prefix(mut self, prefix: &'static str) -> Self {
+        self.context_prefix = Some(prefix);
+        self
+    }
+
     pub fn asset_path<P: AsRef<str>>(mut self, asset_path: P) -> Self {
         self.asset_path = Some(asset_path.as_ref().to_owned());
 
         self
     }
 
-    pub fn into_config(self) -> InertiaConfig {
-        let layout = Box::new(move |props| {
-            let main_path = match &self.asset_path {
-                Some(asset_path) => format!("/{}/{}", asset_path, self.main.file),
-                None => format!("/{}", self.main.file),
-            };
-            let main_integrity = self.main.integrity.clone();
-
-            if let Some(template_engine) = &self.template_engine {
-                let mut context = TeraContext::new();
-
-                context.insert("vite_client","");
-                context.insert("vite_react_refresh", "");
+    /// Preloads a web font in the html head.
+    ///
+    /// Fonts referenced by CSS are discovered late by the browser;
+    /// preloading the primary fonts avoids a flash of invisible or
+    /// unstyled text. If `path` is a key in the manifest it is
+    /// resolved to the built file, otherwise it is used verbatim. May
+    /// be called multiple times.
+    pub fn preload_font<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.fonts.push(path.as_ref().to_owned());
 
-                let vite_main = match main_integrity {
-                    Some(main_integrity) => {
-                        html! {
-                            script type="module" src=(main_path) integrity=(main_integrity) {}
-                        }.into_string()
-                    },
-                    None => {
-                        html! {
-                            script type="module" src=(main_path) {}
-                        }.into_string()
-                    }
-                };
+        self
+    }
 
-                context.insert(
-                    "vite_main",
-                    &vite_main,
-                );
+    /// Adds a `Link` header to initial page loads that preloads the
+    /// main module, its css and any [preloaded fonts](Self::preload_font).
+    ///
+    /// Servers and CDNs that support [103 Early Hints] can use this to
+    /// start fetching assets before the html is ready.
+    ///
+    /// [103 Early Hints]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/103
+    pub fn link_header(mut self) -> Self {
+        self.link_header = true;
+        self
+    }
 
-                let app_element = html! {
-                    div #app data-page=(props) {}
-                }
-                .into_string();
-                context.insert("application", &app_element);
+    /// Rewrites every asset url (main script, stylesheets and
+    /// preloads) with `rewriter` when rendering, e.g. to pick a CDN
+    /// host. `rewriter` gets the url as it would be rendered otherwise.
+    ///
+    /// The asset tags are rebuilt for every initial page load, so
+    /// `rewriter` can vary its result, e.g. by region from a task
+    /// local. The [Link header](Self::link_header) is built once.
+    pub fn asset_url_rewriter<F>(mut self, rewriter: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.asset_url_rewriter = Some(Box::new(rewriter));
+        self
+    }
 
-                match &self.layout_template {
-                    Some(layout_template) => {
-                        match template_engine.render(layout_template, &context) {
-                            Ok(output) => output,
-                            Err(err) => {
-                                eprintln!("Failed to render template {err}");
-                                "".to_string()
-                            }
-                        }
-                    },
-                    None => "".to_string()
-                }
-            } else {
-                let css = self.css.clone().unwrap_or("".to_string());
-                html! {
-                    html lang=(self.lang) {
-                        head {
-                            title { (self.title) }
-                            meta charset="utf-8";
-                            meta name="viewport" content="width=device-width, initial-scale=1.0";
-                            @if let Some(integrity) = main_integrity {
-                                script type="module" src=(main_path) integrity=(integrity) {}
-                            } else {
-                                script type="module" src=(main_path) {}
-                            }
-                            (PreEscaped(css))
-                        }
-                        body {
-                            div #app data-page=(props) {}
-                        }
-                    }
-                }
-                .into_string()
-            }
+    /// Adds `defer` to the main script tag. The other scripts of an
+    /// [entrypoint](Entrypoint), like its runtime and vendors, are left
+    /// as they are.
+    ///
+    /// Module scripts are deferred anyway, but some setups rely on the
+    /// explicit attribute. Replaces [script_async](Self::script_async):
+    /// whichever is called last wins.
+    pub fn script_defer(mut self) -> Self {
+        self.script_loading = Some(ScriptLoading::Defer);
+        self
+    }
 
-        });
-        InertiaConfig::new(Some(self.version), layout)
+    /// Adds `async` to the main script tag, so it runs as soon as it's
+    /// loaded. The other scripts of an [entrypoint](Entrypoint) are left
+    /// as they are, so they still run in order. Replaces
+    /// [script_defer](Self::script_defer): whichever is called last
+    /// wins.
+    pub fn script_async(mut self) -> Self {
+        self.script_loading = Some(ScriptLoading::Async);
+        self
     }
-}
 
-#[derive(Debug)]
-pub enum ViteError {
-    ManifestMissing(std::io::Error),
-    EntryMissing(&'static str),
-}
+    /// Adds a `media` attribute, like `print`, to the stylesheets whose
+    /// manifest path matches `predicate`.
+    ///
+    /// The manifest has no media information, so this is matched on
+    /// file names. The first matching rule wins; other stylesheets get
+    /// no `media` attribute.
+    pub fn css_media<F>(mut self, predicate: F, media: &'static str) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.css_media.push((Box::new(predicate), media));
+        self
+    }
 
-impl std::fmt::Display for ViteError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::ManifestMissing(_) => write!(f, "couldn't open manifest file"),
-            Self::EntryMissing(entry) => write!(f, "manifest missing entry for {}", entry),
-        }
+    /// Adds a `<base href>` for the [base path](Self::base_path) to the
+    /// head, e.g. `<base href="/app/">`.
+    ///
+    /// Opt-in, since it changes how every relative url on the page
+    /// resolves. Templates get the tag as `vite_base`.
+    pub fn base_tag(mut self) -> Self {
+        self.base_tag = true;
+        self
     }
-}
 
-impl std::error::Error for ViteError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Self::ManifestMissing(e) => Some(e),
-            _ => None,
-        }
+    /// Adds `<meta name="build" content="...">` to the head, e.g. with
+    /// the commit and build time, so users can report which build they
+    /// saw. Templates get the tag as `vite_build_info`.
+    ///
+    /// Unlike the asset version, this isn't compared with the client.
+    pub fn build_info<I: AsRef<str>>(mut self, build_info: I) -> Self {
+        self.build_info = Some(build_info.as_ref().to_owned());
+        self
     }
-}
+
+    /// Sets the path prefix the app is mounted under, e.g. `/app`.
+    ///
+    /// Unlike [asset_path](Self::asset_path), which is where assets are
+    /// served from, this is the app's own mount point: it prefixes
+    /// both the asset urls and the page `url` (see
+    /// [InertiaConfig::base_path]).
+    pub fn base_path<P: AsRef<str>>(mut self, base_path: P) -> Self {
+        self.base_path = normalize_base_path(base_path.as_ref());
+
+        self
+    }
+
+    /// Sets the `crossorigin` attribute (`anonymous` or
+    /// `use-credentials`) of the main script, stylesheet and preload
+    /// links, e.g. for assets on a CDN.
+    ///
+    /// The same mode is used for the [Link header](Self::link_header),
+    /// so preloaded responses can be reused.
+    pub fn crossorigin(mut self, crossorigin: &'static str) -> Self {
+        self.crossorigin = Some(crossorigin);
+        self
+    }
+
+    /// Sets the `referrerpolicy` attribute of every asset tag.
+    pub fn referrer_policy(mut self, referrer_policy: &'static str) -> Self {
+        self.referrer_policy = Some(referrer_policy);
+        self
+    }
+
+    /// Names of the entry points in the manifest, sorted.
+    ///
+    /// These are the keys marked `isEntry`, or every key for
+    /// manifests that don't use the flag at all.
+    pub fn entry_names(&self) -> Vec<&str> {
+        let flagged = self.manifest.values().any(|entry| entry.is_entry.is_some());
+        let mut names: Vec<&str> = self
+            .manifest
+            .iter()
+            .filter(|(_, entry)| !flagged || entry.is_entry == Some(true))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Whether the manifest file is older than `source_modified`, the
+    /// last change to the frontend sources, i.e. the build needs to be
+    /// rerun. Useful when previewing a production build locally.
+    ///
+    /// A directory's modification time only changes when entries are
+    /// added or removed, not when the files in it are edited, so find
+    /// the newest file instead:
+    ///
+    /// ```rust,no_run
+    /// # use axum_inertia::vite;
+    /// use std::path::Path;
+    /// use std::time::SystemTime;
+    ///
+    /// fn newest_modified(path: &Path) -> std::io::Result<SystemTime> {
+    ///     let metadata = std::fs::metadata(path)?;
+    ///     let mut newest = metadata.modified()?;
+    ///     if metadata.is_dir() {
+    ///         for entry in std::fs::read_dir(path)? {
+    ///             newest = newest.max(newest_modified(&entry?.path())?);
+    ///         }
+    ///     }
+    ///     Ok(newest)
+    /// }
+    ///
+    /// let production = vite::Production::new("dist/.vite/manifest.json", "src/main.ts").unwrap();
+    /// if production.is_stale(newest_modified(Path::new("src")).unwrap()) {
+    ///     eprintln!("Warning: the vite build is out of date, run `npm run build`");
+    /// }
+    /// ```
+    ///
+    /// Always `false` for manifests that weren't read from a file.
+    pub fn is_stale(&self, source_modified: SystemTime) -> bool {
+        self.manifest_modified
+            .is_some_and(|manifest_modified| manifest_modified < source_modified)
+    }
+
+    /// The resolved asset configuration, for troubleshooting
+    /// deployments (wrong asset paths, a stale version, ...): the
+    /// version, the main script and stylesheet urls, the asset and base
+    /// paths and the manifest's entry points.
+    ///
+    /// Only build metadata is included, never the layout or headers.
+    pub fn debug_info(&self) -> serde_json::Value {
+        let css: Vec<String> = self
+            .css_files()
+            .into_iter()
+            .map(|file| self.asset_url(file))
+            .collect();
+        serde_json::json!({
+            "environment": "production",
+            "version": self.version,
+            "main": self.asset_url(&self.main.file),
+            "css": css,
+            "asset_path": self.asset_path,
+            "base_path": self.base_path,
+            "entries": self.entry_names(),
+        })
+    }
+
+    /// A handler responding with the [debug_info](Self::debug_info) as
+    /// json, to mount on a route explicitly:
+    ///
+    /// ```rust,no_run
+    /// # use axum::{routing::get, Router};
+    /// # use axum_inertia::vite;
+    /// let production = vite::Production::new("dist/.vite/manifest.json", "src/main.ts").unwrap();
+    /// let app: Router = Router::new().route("/_debug/assets", get(production.debug_handler()));
+    /// let inertia = production.into_config();
+    /// ```
+    ///
+    /// The info is taken when this is called. Consider guarding the
+    /// route, e.g. behind authentication or an environment check.
+    pub fn debug_handler(
+        &self,
+    ) -> impl FnOnce() -> std::future::Ready<axum::Json<serde_json::Value>> + Clone + Send + 'static
+    {
+        let info = self.debug_info();
+        move || std::future::ready(axum::Json(info))
+    }
+
+    /// Serves the manifest's `file` paths by their location under
+    /// `public_root`, decoupling the build layout from the url layout.
+    ///
+    /// Three paths are involved:
+    ///
+    /// - the manifest file, e.g. `public/build/.vite/manifest.json`,
+    ///   as passed to [new](Self::new),
+    /// - the build directory the `file` paths are relative to
+    ///   (`build.outDir`). This is the [output_dir](Self::output_dir)
+    ///   if set, otherwise the manifest's directory, without the
+    ///   `.vite` directory vite 5 writes the manifest to,
+    /// - the public root, e.g. `public`, the directory the assets are
+    ///   served from (the one given to `ServeDir`). Its url prefix is
+    ///   set by [asset_path](Self::asset_path) and
+    ///   [base_path](Self::base_path).
+    ///
+    /// So a `file` of `assets/main.js` in
+    /// `public/build/.vite/manifest.json` is served as
+    /// `/build/assets/main.js`. Pass both paths the same way, i.e.
+    /// both relative to the working directory or both absolute.
+    ///
+    /// Files outside of `public_root` can't be served;
+    /// [try_into_config](Self::try_into_config) fails with
+    /// [ViteError::OutsidePublicRoot] and
+    /// [into_config](Self::into_config) panics. Manifests that
+    /// weren't read from a path (and have no `output_dir`) are used
+    /// as is.
+    pub fn public_root<P: AsRef<Path>>(mut self, public_root: P) -> Self {
+        self.public_root = Some(public_root.as_ref().to_owned());
+
+        self
+    }
+
+    /// The directory the manifest's `file` paths are relative to, see
+    /// [public_root](Self::public_root).
+    fn build_dir(&self) -> Option<PathBuf> {
+        if let Some(output_dir) = &self.output_dir {
+            return Some(output_dir.clone());
+        }
+        let manifest_dir = self.manifest_dir.as_ref()?;
+        match manifest_dir.file_name() {
+            Some(name) if name == ".vite" => manifest_dir.parent().map(Path::to_path_buf),
+            _ => Some(manifest_dir.clone()),
+        }
+    }
+
+    /// The url path of manifest `file`, see
+    /// [public_root](Self::public_root).
+    fn served_file<'a>(&self, file: &'a str) -> Result<Cow<'a, str>, ViteError> {
+        let (Some(build_dir), Some(public_root)) = (self.build_dir(), &self.public_root) else {
+            return Ok(Cow::Borrowed(file));
+        };
+        if is_absolute_url(file) {
+            return Ok(Cow::Borrowed(file));
+        }
+        match normalize_path(&build_dir.join(file)).strip_prefix(normalize_path(public_root)) {
+            Ok(path) => Ok(Cow::Owned(path.to_string_lossy().replace('\\', "/"))),
+            Err(_) => Err(ViteError::OutsidePublicRoot(file.to_string())),
+        }
+    }
+
+    /// Checks that every linked manifest file is under the
+    /// [public_root](Self::public_root).
+    fn check_public_root(&self) -> Result<(), ViteError> {
+        let fonts = self
+            .fonts
+            .iter()
+            .filter_map(|font| self.manifest.get(font))
+            .map(|entry| entry.file.as_str());
+        let files = std::iter::once(self.main.file.as_str())
+            .chain(self.main.scripts.iter().map(String::as_str))
+            .chain(self.css_files())
+            .chain(fonts);
+        for file in files {
+            self.served_file(file)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the directory vite wrote the build to (`build.outDir`,
+    /// `dist` by default), for options that read the built assets.
+    pub fn output_dir<P: AsRef<Path>>(mut self, output_dir: P) -> Self {
+        self.output_dir = Some(output_dir.as_ref().to_owned());
+
+        self
+    }
+
+    /// Inlines stylesheets smaller than `bytes` into the html head as
+    /// `<style>` elements, saving a round trip for critical css.
+    ///
+    /// The files are read from the [output_dir](Self::output_dir)
+    /// when the config is built; larger (or unreadable) stylesheets
+    /// are linked as usual.
+    pub fn inline_css_below(mut self, bytes: usize) -> Self {
+        self.inline_css_below = Some(bytes);
+
+        self
+    }
+
+    /// Appends raw html to the head of the default layout, e.g. meta
+    /// tags or a favicon link. May be called multiple times.
+    ///
+    /// Ignored when using a [template engine](Self::template_engine).
+    pub fn head_html<H: AsRef<str>>(mut self, html: H) -> Self {
+        self.head_html.push_str(html.as_ref());
+        self
+    }
+
+    /// Leaves the `title`, `charset` and `viewport` tags out of the
+    /// default layout, to supply them with [head_html](Self::head_html)
+    /// instead.
+    pub fn minimal_head(mut self) -> Self {
+        self.minimal_head = true;
+        self
+    }
+
+    /// Renders the scripts as module scripts (`type="module"`, the
+    /// default) or, with `false`, as classic scripts.
+    ///
+    /// Vite's output needs module scripts; classic scripts are only
+    /// for unusual setups.
+    pub fn module_type(mut self, module: bool) -> Self {
+        self.module_type = module;
+        self
+    }
+
+    /// Sets the doctype of the default layout, `<!DOCTYPE html>` by
+    /// default, e.g. for XHTML. `None` leaves it out.
+    pub fn doctype(mut self, doctype: Option<&'static str>) -> Self {
+        self.doctype = doctype;
+        self
+    }
+
+    /// Adds a `Content-Security-Policy` header to initial page loads
+    /// that only allows same-origin scripts and styles, plus inlined
+    /// css and the origins of external [fonts](Self::preload_font)
+    /// and of assets moved to other hosts by an
+    /// [asset_url_rewriter](Self::asset_url_rewriter).
+    ///
+    /// Inline scripts are not allowed, so the script of
+    /// [InertiaConfig::window_data](crate::InertiaConfig::window_data)
+    /// (and any inline script in [head_html](Self::head_html) or a
+    /// layout template) is blocked; use [csp_policy](Self::csp_policy)
+    /// with a hash or nonce for those.
+    ///
+    /// See [csp_policy](Self::csp_policy) to send a policy of your own.
+    pub fn csp(mut self) -> Self {
+        self.csp = Some(Csp::Default);
+        self
+    }
+
+    /// Adds a `Content-Security-Policy` header with `policy` to
+    /// initial page loads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `policy` isn't a valid header value, e.g. because it
+    /// contains a newline.
+    pub fn csp_policy<P: AsRef<str>>(mut self, policy: P) -> Self {
+        self.csp = Some(Csp::Policy(valid_csp(policy.as_ref())));
+        self
+    }
+
+    /// The policy sent by [csp](Self::csp).
+    fn default_csp(&self) -> String {
+        let inline_styles = if self.inline_css_below.is_some() {
+            " 'unsafe-inline'"
+        } else {
+            ""
+        };
+        let script_origins = source_list(
+            std::iter::once(self.main.file.as_str())
+                .chain(self.main.scripts.iter().map(String::as_str))
+                .map(|file| self.asset_url(file)),
+        );
+        let style_origins = source_list(
+            self.css_files()
+                .into_iter()
+                .map(|file| self.asset_url(file)),
+        );
+        let font_origins = source_list(self.font_urls());
+        format!(
+            "default-src 'self'; script-src 'self'{script_origins}; \
+             style-src 'self'{inline_styles}{style_origins}; \
+             img-src 'self' data:; font-src 'self'{font_origins}; object-src 'none'; \
+             base-uri 'self'"
+        )
+    }
+
+    fn asset_url(&self, file: &str) -> String {
+        if is_absolute_url(file) {
+            return file.to_string();
+        }
+        let file = self
+            .served_file(file)
+            .unwrap_or_else(|error| panic!("{}", error));
+        let url = match &self.asset_path {
+            Some(asset_path) => format!("{}/{}/{}", self.base_path, asset_path, file),
+            None => format!("{}/{}", self.base_path, file),
+        };
+        match &self.asset_url_rewriter {
+            Some(rewrite) => rewrite(&url),
+            None => url,
+        }
+    }
+
+    /// Stylesheets for the main entry followed by those of every chunk
+    /// it (transitively) imports, without duplicates.
+    ///
+    /// See: https://vitejs.dev/guide/backend-integration
+    fn css_files(&self) -> Vec<&str> {
+        let mut sources: Vec<&str> = Vec::new();
+        let chunks = std::iter::once(&self.main).chain(self.imported_chunks());
+        for source in chunks.flat_map(|chunk| chunk.css.iter().flatten()) {
+            if !sources.contains(&source.as_str()) {
+                sources.push(source);
+            }
+        }
+        sources
+    }
+
+    /// The contents of `file` if it should be inlined, see
+    /// [inline_css_below](Self::inline_css_below).
+    fn inline_css(&self, file: &str) -> Option<String> {
+        let limit = self.inline_css_below?;
+        let build_dir = match &self.public_root {
+            Some(_) => self.build_dir()?,
+            None => self.output_dir.clone()?,
+        };
+        let css = std::fs::read_to_string(build_dir.join(file)).ok()?;
+        // A closing tag would end the style element early.
+        let closes_style = css.to_ascii_lowercase().contains("</style");
+        (css.len() < limit && !closes_style).then_some(css)
+    }
+
+    fn stylesheets(&self) -> String {
+        html! {
+            @for file in self.css_files() {
+                @match self.inline_css(file) {
+                    // Inlined css can't close the element, see
+                    // inline_css.
+                    Some(css) => style media=[self.media(file)] { (PreEscaped(css)) },
+                    None => link
+                        rel="stylesheet"
+                        href=(self.asset_url(file))
+                        media=[self.media(file)]
+                        crossorigin=[self.crossorigin]
+                        referrerpolicy=[self.referrer_policy];,
+                }
+            }
+        }
+        .into_string()
+    }
+
+    /// The [css_media](Self::css_media) for the stylesheet `file`.
+    fn media(&self, file: &str) -> Option<&str> {
+        self.css_media
+            .iter()
+            .find(|(matches, _)| matches(file))
+            .map(|(_, media)| media.as_ref())
+    }
+
+    fn script_type(&self) -> Option<&'static str> {
+        self.module_type.then_some("module")
+    }
+
+    fn main_script(&self) -> String {
+        let dependencies = html! {
+            @for script in &self.main.scripts {
+                script
+                    type=[self.script_type()]
+                    src=(self.asset_url(script))
+                    crossorigin=[self.crossorigin]
+                    referrerpolicy=[self.referrer_policy] {}
+            }
+        };
+        let main = html! {
+            script
+                type=[self.script_type()]
+                src=(self.asset_url(&self.main.file))
+                integrity=[&self.main.integrity]
+                crossorigin=[self.crossorigin]
+                referrerpolicy=[self.referrer_policy]
+                defer[self.script_loading == Some(ScriptLoading::Defer)] {}
+        }
+        .into_string();
+        // `async` is a keyword, so maud can't render it as an attribute.
+        let main = match self.script_loading {
+            Some(ScriptLoading::Async) => main.replacen("></script>", " async></script>", 1),
+            _ => main,
+        };
+        format!("{}{}", dependencies.into_string(), main)
+    }
+
+    /// Chunks imported by the main entry, dependencies first.
+    fn imported_chunks(&self) -> Vec<&ManifestEntry> {
+        fn visit<'a>(
+            manifest: &'a HashMap<String, ManifestEntry>,
+            chunk: &'a ManifestEntry,
+            seen: &mut Vec<&'a str>,
+            chunks: &mut Vec<&'a ManifestEntry>,
+        ) {
+            for name in &chunk.imports {
+                if seen.contains(&name.as_str()) {
+                    continue;
+                }
+                seen.push(name);
+                if let Some(import) = manifest.get(name) {
+                    visit(manifest, import, seen, chunks);
+                    chunks.push(import);
+                }
+            }
+        }
+
+        let mut chunks = Vec::new();
+        visit(&self.manifest, &self.main, &mut Vec::new(), &mut chunks);
+        chunks
+    }
+
+    fn font_urls(&self) -> Vec<String> {
+        self.fonts
+            .iter()
+            .map(|font| match self.manifest.get(font) {
+                Some(entry) => self.asset_url(&entry.file),
+                None => font.clone(),
+            })
+            .collect()
+    }
+
+    fn build_link_header(&self) -> String {
+        let crossorigin = match self.crossorigin {
+            Some("use-credentials") => "; crossorigin=use-credentials",
+            Some(_) => "; crossorigin",
+            None => "",
+        };
+        let referrer_policy = self
+            .referrer_policy
+            .map(|policy| format!("; referrerpolicy={policy}"))
+            .unwrap_or_default();
+        let mut links = vec![format!(
+            "<{}>; rel=modulepreload{crossorigin}{referrer_policy}",
+            self.asset_url(&self.main.file)
+        )];
+        for file in self.css_files() {
+            if self.inline_css(file).is_none() {
+                let css = self.asset_url(file);
+                links.push(format!(
+                    "<{css}>; rel=preload; as=style{crossorigin}{referrer_policy}"
+                ));
+            }
+        }
+        // Fonts are always fetched in cors mode.
+        let font_crossorigin = if crossorigin.is_empty() {
+            "; crossorigin"
+        } else {
+            crossorigin
+        };
+        for font in self.font_urls() {
+            links.push(format!(
+                "<{font}>; rel=preload; as=font{font_crossorigin}{referrer_policy}"
+            ));
+        }
+        links.join(", ")
+    }
+
+    fn font_preloads(&self) -> String {
+        html! {
+            @for (font, href) in self.fonts.iter().zip(self.font_urls()) {
+                @if let Some(crossorigin) = self.crossorigin {
+                    link rel="preload" href=(href) as="font" type=[font_mime_type(font)] crossorigin=(crossorigin) referrerpolicy=[self.referrer_policy];
+                } @else {
+                    link rel="preload" href=(href) as="font" type=[font_mime_type(font)] crossorigin referrerpolicy=[self.referrer_policy];
+                }
+            }
+        }
+        .into_string()
+    }
+
+    /// Like [into_config](Self::into_config), but checks the layout
+    /// template first.
+    ///
+    /// The template must exist in the [template
+    /// engine](Self::template_engine). It is dry-rendered and must
+    /// include the mount element (i.e. use `{{ application | safe }}`,
+    /// or build an element with `id="{{ root_id }}"` and the
+    /// `data_page` in its `data-page` attribute), otherwise the page
+    /// would render without anywhere for the app to boot. With a [public_root](Self::public_root), all linked
+    /// manifest files must be under it.
+    pub fn try_into_config(self) -> Result<InertiaConfig, ViteError> {
+        self.check_public_root()?;
+        if let (Some(engine), Some(layout_template)) =
+            (&self.template_engine, &self.layout_template)
+        {
+            check_template_exists(engine, layout_template)?;
+        }
+        let layout_template = self.layout_template.clone();
+        let root_id = self.root_id;
+        let config = self.into_config();
+        if let Some(layout_template) = layout_template {
+            check_application_rendered(&config, layout_template, root_id)?;
+        }
+        Ok(config)
+    }
+
+    pub fn into_config(self) -> InertiaConfig {
+        let version = self.version.clone();
+        let base_path = self.base_path.clone();
+        let font_preloads = self.font_preloads();
+        let css = self.stylesheets();
+        let link_header = self.link_header.then(|| self.build_link_header());
+        let csp = csp_header(&self.csp, || self.default_csp());
+        let main_script = self.main_script();
+        let base_tag = if self.base_tag {
+            html! { base href=(format!("{}/", self.base_path)); }.into_string()
+        } else {
+            String::new()
+        };
+        let build_info = html! {
+            @if let Some(build_info) = &self.build_info {
+                meta name="build" content=(build_info);
+            }
+        }
+        .into_string();
+        let layout = Box::new(move |props| {
+            let (main_script, font_preloads, css): (Cow<str>, Cow<str>, Cow<str>) =
+                match self.asset_url_rewriter {
+                    Some(_) => (
+                        Cow::Owned(self.main_script()),
+                        Cow::Owned(self.font_preloads()),
+                        Cow::Owned(self.stylesheets()),
+                    ),
+                    None => (
+                        Cow::Borrowed(&main_script),
+                        Cow::Borrowed(&font_preloads),
+                        Cow::Borrowed(&css),
+                    ),
+                };
+            if let Some(template_engine) = &self.template_engine {
+                let mut context = TeraContext::new();
+
+                context.insert("vite_base", &base_tag);
+                context.insert("vite_build_info", &build_info);
+                context.insert("vite_client", "");
+                context.insert("vite_react_refresh", "");
+                context.insert("vite_main", &main_script);
+                context.insert("vite_fonts", &font_preloads);
+
+                let app_element = html! {
+                    div id=(self.root_id) data-page=(props) {}
+                }
+                .into_string();
+                context.insert("application", &app_element);
+                context.insert("data_page", &props);
+                context.insert("root_id", self.root_id);
+                context.insert("lang", self.lang);
+
+                let context = prefixed_context(context, self.context_prefix);
+                match &self.layout_template {
+                    Some(layout_template) => Ok(template_engine.render(layout_template, &context)?),
+                    None => Ok("".to_string()),
+                }
+            } else {
+                Ok(html! {
+                    (PreEscaped(self.doctype.unwrap_or_default()))
+                    html lang=(self.lang) {
+                        head {
+                            (PreEscaped(&base_tag))
+                            @if !self.minimal_head {
+                                title { (self.title) }
+                                meta charset="utf-8";
+                                meta name="viewport" content="width=device-width, initial-scale=1.0";
+                            }
+                            (PreEscaped(&build_info))
+                            (PreEscaped(&*main_script))
+                            (PreEscaped(&*font_preloads))
+                            (PreEscaped(&*css))
+                            (PreEscaped(&self.head_html))
+                        }
+                        body {
+                            div id=(self.root_id) data-page=(props) {}
+                        }
+                    }
+                }
+                .into_string())
+            }
+        });
+        let mut config = InertiaConfig::new_fallible(Some(version), layout).base_path(&base_path);
+        if let Some(link) = link_header.and_then(|link| HeaderValue::from_str(&link).ok()) {
+            config = config.html_header(LINK, link);
+        }
+        match csp {
+            Some(csp) => config.html_header(CONTENT_SECURITY_POLICY, csp),
+            None => config,
+        }
+    }
+}
+
+/// Nests the template variables under `prefix`, if any.
+fn prefixed_context(context: TeraContext, prefix: Option<&str>) -> TeraContext {
+    match prefix {
+        Some(prefix) => {
+            let mut prefixed = TeraContext::new();
+            prefixed.insert(prefix, &context.into_json());
+            prefixed
+        }
+        None => context,
+    }
+}
+
+const HTML5_DOCTYPE: &str = "<!DOCTYPE html>";
+
+/// Percent-encodes the characters of `query` that aren't allowed in
+/// a url query, leaving existing escapes alone.
+fn encode_query(query: &str) -> String {
+    let mut encoded = String::with_capacity(query.len());
+    for byte in query.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/?%".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// A `Content-Security-Policy` for the layout.
+enum Csp {
+    /// A policy derived from the configuration.
+    Default,
+    Policy(String),
+}
+
+fn csp_header(csp: &Option<Csp>, default: impl FnOnce() -> String) -> Option<HeaderValue> {
+    let policy = match csp.as_ref()? {
+        Csp::Default => default(),
+        Csp::Policy(policy) => policy.clone(),
+    };
+    Some(HeaderValue::from_str(&policy).expect("invalid Content-Security-Policy"))
+}
+
+/// Checks that `policy` can be sent as a header, see
+/// [Production::csp_policy].
+fn valid_csp(policy: &str) -> String {
+    if let Err(err) = HeaderValue::from_str(policy) {
+        panic!("invalid Content-Security-Policy {:?}: {}", policy, err);
+    }
+    policy.to_owned()
+}
+
+/// The distinct origins of the absolute `urls`, each with a leading
+/// space, for a CSP source list.
+fn source_list(urls: impl IntoIterator<Item = String>) -> String {
+    let mut origins: Vec<String> = Vec::new();
+    for url in urls {
+        if let Some(origin) = url_origin(&url) {
+            if !origins.iter().any(|o| o == origin) {
+                origins.push(origin.to_owned());
+            }
+        }
+    }
+    origins.iter().map(|origin| format!(" {origin}")).collect()
+}
+
+/// Whether `url` is absolute, or protocol-relative (`//host/...`).
+fn is_absolute_url(url: &str) -> bool {
+    url.starts_with("//") || url.contains("://")
+}
+
+/// The `scheme://host[:port]` part of an absolute url.
+fn url_origin(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let end = url.len() - rest.len() + rest.find('/').unwrap_or(rest.len());
+    Some(&url[..end])
+}
+
+fn font_mime_type(path: &str) -> Option<&'static str> {
+    let extension = path.rsplit_once('.')?.1;
+    match extension.to_ascii_lowercase().as_str() {
+        "woff2" => Some("font/woff2"),
+        "woff" => Some("font/woff"),
+        "ttf" => Some("font/ttf"),
+        "otf" => Some("font/otf"),
+        _ => None,
+    }
+}
+
+/// Checks that `engine` has a template named `layout_template`,
+/// which otherwise fails every render.
+fn check_template_exists(engine: &Tera, layout_template: &str) -> Result<(), ViteError> {
+    if engine
+        .get_template_names()
+        .any(|name| name == layout_template)
+    {
+        Ok(())
+    } else {
+        Err(ViteError::TemplateMissing(layout_template.to_string()))
+    }
+}
+
+/// Dry-renders the layout and makes sure the mount element made it
+/// into the output.
+///
+/// Templates may build the mount element by hand (from `root_id` and
+/// `data_page`), so this looks for a tag with the root id whose
+/// `data-page` attribute holds the page data, not for the exact
+/// `application` markup.
+fn check_application_rendered(
+    config: &InertiaConfig,
+    layout_template: String,
+    root_id: &str,
+) -> Result<(), ViteError> {
+    let probe = r#"{"component":"__probe__"}"#;
+    let rendered = (config.layout())(probe.to_string());
+    let mounted = rendered.split('<').skip(1).any(|tag| {
+        let tag = tag.split('>').next().unwrap_or_default();
+        attribute(tag, "id") == Some(root_id)
+            && attribute(tag, "data-page").is_some_and(|page| page.contains("__probe__"))
+    });
+
+    if mounted {
+        Ok(())
+    } else {
+        Err(ViteError::ApplicationMissing(layout_template))
+    }
+}
+
+/// The quoted value of the attribute `name` in the start `tag`.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    ['"', '\''].into_iter().find_map(|quote| {
+        let start = tag
+            .match_indices(&format!("{name}={quote}"))
+            .find(|(at, _)| tag[..*at].ends_with(char::is_whitespace))?
+            .0
+            + name.len()
+            + 2;
+        let end = tag[start..].find(quote)?;
+        Some(&tag[start..start + end])
+    })
+}
+
+/// Resolves `.` and `..` in `path` without touching the filesystem.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Finds the manifest entry named `main`, which may be a pattern with
+/// `*` wildcards matching exactly one entry.
+fn find_entry<'a>(
+    manifest: &'a HashMap<String, ManifestEntry>,
+    main: &'static str,
+) -> Result<&'a ManifestEntry, ViteError> {
+    if let Some(entry) = manifest.get(main) {
+        return Ok(entry);
+    }
+    if !main.contains('*') {
+        return Err(ViteError::EntryMissing(main));
+    }
+    let mut matches: Vec<(&String, &ManifestEntry)> = manifest
+        .iter()
+        .filter(|(name, _)| wildcard_match(main, name))
+        .collect();
+    match matches.len() {
+        0 => Err(ViteError::EntryMissing(main)),
+        1 => Ok(matches.remove(0).1),
+        _ => {
+            let mut names: Vec<String> =
+                matches.into_iter().map(|(name, _)| name.clone()).collect();
+            names.sort();
+            Err(ViteError::AmbiguousEntry {
+                pattern: main,
+                matches: names,
+            })
+        }
+    }
+}
+
+/// Whether `integrity` is a single well-formed SRI hash.
+fn valid_integrity(integrity: &str) -> bool {
+    let Some((algorithm, digest)) = integrity.split_once('-') else {
+        return false;
+    };
+    let length = match algorithm {
+        "sha256" => 32,
+        "sha384" => 48,
+        "sha512" => 64,
+        _ => return false,
+    };
+    // Hashes may carry options after a `?`.
+    let digest = digest.split('?').next().unwrap_or_default();
+    BASE64_STANDARD
+        .decode(digest)
+        .is_ok_and(|digest| digest.len() == length)
+}
+
+/// Matches `name` against `pattern`, where `*` matches any (possibly
+/// empty) sequence of characters.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let last = parts.pop();
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    match last {
+        Some(last) => rest.ends_with(last),
+        None => rest.is_empty(),
+    }
+}
+
+#[derive(Debug)]
+pub enum ViteError {
+    ManifestMissing(std::io::Error),
+    EntryMissing(&'static str),
+    /// A manifest entry has a malformed `integrity` hash, see
+    /// [Production::validate_integrity].
+    InvalidIntegrity {
+        entry: String,
+    },
+    /// More than one manifest entry matches the entry pattern.
+    AmbiguousEntry {
+        pattern: &'static str,
+        matches: Vec<String>,
+    },
+    /// The manifest file exists but has no content.
+    EmptyManifest,
+    /// The template engine has no layout template with the given name.
+    TemplateMissing(String),
+    /// The layout template didn't render the `application` context key.
+    ApplicationMissing(String),
+    /// A manifest entry couldn't be parsed, e.g. because it has no
+    /// `file`.
+    MalformedEntry {
+        name: String,
+        detail: String,
+    },
+    /// A manifest file isn't under the public root, see
+    /// [Production::public_root].
+    OutsidePublicRoot(String),
+}
+
+impl std::fmt::Display for ViteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ManifestMissing(_) => write!(f, "couldn't open manifest file"),
+            Self::EntryMissing(entry) => write!(f, "manifest missing entry for {}", entry),
+            Self::InvalidIntegrity { entry } => {
+                write!(f, "malformed integrity hash for manifest entry {}", entry)
+            }
+            Self::AmbiguousEntry { pattern, matches } => write!(
+                f,
+                "manifest entry pattern {} matches several entries: {}",
+                pattern,
+                matches.join(", ")
+            ),
+            Self::EmptyManifest => write!(
+                f,
+                "manifest file is empty; has the frontend been built (e.g. `npm run build`)?"
+            ),
+            Self::TemplateMissing(template) if template.is_empty() => {
+                write!(f, "layout template name is empty")
+            }
+            Self::TemplateMissing(template) => {
+                write!(f, "template engine has no layout template {}", template)
+            }
+            Self::ApplicationMissing(template) => write!(
+                f,
+                "layout template {} does not render the application (missing `{{{{ application | safe }}}}`?)",
+                template
+            ),
+            Self::MalformedEntry { name, detail } => {
+                write!(f, "malformed manifest entry {}: {}", name, detail)
+            }
+            Self::OutsidePublicRoot(file) => {
+                write!(f, "manifest file {} is outside of the public root", file)
+            }
+        }
+    }
+}
+
+/// Compares [ManifestMissing](ViteError::ManifestMissing) errors by
+/// their [io::ErrorKind](std::io::ErrorKind), since `io::Error`
+/// itself isn't comparable.
+impl PartialEq for ViteError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::ManifestMissing(a), Self::ManifestMissing(b)) => a.kind() == b.kind(),
+            (Self::EntryMissing(a), Self::EntryMissing(b)) => a == b,
+            (Self::InvalidIntegrity { entry }, Self::InvalidIntegrity { entry: other }) => {
+                entry == other
+            }
+            (
+                Self::AmbiguousEntry { pattern, matches },
+                Self::AmbiguousEntry {
+                    pattern: other_pattern,
+                    matches: other_matches,
+                },
+            ) => pattern == other_pattern && matches == other_matches,
+            (Self::EmptyManifest, Self::EmptyManifest) => true,
+            (Self::TemplateMissing(a), Self::TemplateMissing(b)) => a == b,
+            (Self::ApplicationMissing(a), Self::ApplicationMissing(b)) => a == b,
+            (
+                Self::MalformedEntry { name, detail },
+                Self::MalformedEntry {
+                    name: other_name,
+                    detail: other_detail,
+                },
+            ) => name == other_name && detail == other_detail,
+            (Self::OutsidePublicRoot(a), Self::OutsidePublicRoot(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for ViteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ManifestMissing(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Deserialize, Clone)]
 struct ManifestEntry {
     file: String,
     integrity: Option<String>,
     css: Option<Vec<String>>,
+    #[serde(default)]
+    imports: Vec<String>,
+    #[serde(default, rename = "isEntry")]
+    is_entry: Option<bool>,
+    /// Scripts to load before `file`, for [other formats](ManifestFormat)
+    /// that split an entry into several scripts.
+    #[serde(skip)]
+    scripts: Vec<String>,
+}
+
+/// A build manifest format other than vite's, see
+/// [Production::from_format].
+pub trait ManifestFormat {
+    /// Parses `manifest` into its entrypoints by name.
+    fn entrypoints(
+        &self,
+        manifest: &str,
+    ) -> Result<HashMap<String, Entrypoint>, Box<dyn std::error::Error>>;
+}
+
+/// The assets of a build entrypoint, paths relative to the
+/// [base path](Production::base_path) or absolute urls (including
+/// protocol-relative ones like `//cdn.example.com/app.js`), which are
+/// used as is.
+#[derive(Clone, Debug, Default)]
+pub struct Entrypoint {
+    /// The scripts to load, in order; the last one is the main script.
+    pub js: Vec<String>,
+    /// The stylesheets to link, in order.
+    pub css: Vec<String>,
+}
+
+/// The `entrypoints.json` written by webpack (via Symfony Encore or
+/// `webpack-assets-manifest`), e.g.:
+///
+/// ```json
+/// { "entrypoints": { "app": { "js": ["/build/runtime.js", "/build/app.js"], "css": ["/build/app.css"] } } }
+/// ```
+///
+/// The scripts are loaded in order, as module scripts unless
+/// [classic scripts](Production::module_type) are configured.
+pub struct Entrypoints;
+
+impl ManifestFormat for Entrypoints {
+    fn entrypoints(
+        &self,
+        manifest: &str,
+    ) -> Result<HashMap<String, Entrypoint>, Box<dyn std::error::Error>> {
+        #[derive(Deserialize)]
+        struct Manifest {
+            entrypoints: HashMap<String, Assets>,
+        }
+
+        #[derive(Deserialize)]
+        struct Assets {
+            #[serde(default)]
+            js: Vec<String>,
+            #[serde(default)]
+            css: Vec<String>,
+        }
+
+        let relative = |paths: Vec<String>| {
+            paths
+                .into_iter()
+                .map(|path| {
+                    if is_absolute_url(&path) {
+                        path
+                    } else {
+                        path.trim_start_matches('/').to_string()
+                    }
+                })
+                .collect()
+        };
+        let manifest: Manifest = serde_json::from_str(manifest)?;
+        Ok(manifest
+            .entrypoints
+            .into_iter()
+            .map(|(name, assets)| {
+                let entrypoint = Entrypoint {
+                    js: relative(assets.js),
+                    css: relative(assets.css),
+                };
+                (name, entrypoint)
+            })
+            .collect())
+    }
+}
+
+/// Every field vite writes to a manifest entry, see
+/// [Production::new_strict].
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[allow(dead_code)]
+struct StrictManifestEntry {
+    file: String,
+    src: Option<String>,
+    name: Option<String>,
+    names: Option<Vec<String>>,
+    is_entry: Option<bool>,
+    is_dynamic_entry: Option<bool>,
+    imports: Option<Vec<String>>,
+    dynamic_imports: Option<Vec<String>>,
+    css: Option<Vec<String>>,
+    assets: Option<Vec<String>>,
+    integrity: Option<String>,
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::redundant_pattern_matching)]
 mod tests {
     use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_development_default() {
+        let development = Development::default();
+
+        assert_eq!(development.port, 5173);
+        assert_eq!(development.main, "src/main.ts");
+        assert_eq!(development.lang, "en");
+        assert_eq!(development.title, "Inertia App");
+        assert_eq!(development.react, false);
+    }
+
+    #[test]
+    fn test_development_builder_methods() {
+        let development = Development::default()
+            .port(8080)
+            .main("src/deep/index.ts")
+            .lang("id")
+            .title("Untitled Axum Inertia App")
+            .react();
+
+        assert_eq!(development.port, 8080);
+        assert_eq!(development.main, "src/deep/index.ts");
+        assert_eq!(development.lang, "id");
+        assert_eq!(development.title, "Untitled Axum Inertia App");
+        assert_eq!(development.react, true);
+    }
+
+    #[test]
+    fn test_development_browser_facing_host() {
+        let config = Development::default()
+            .host("host.docker.internal")
+            .port(5173)
+            .main("src/main.ts")
+            .into_config();
+
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(
+            r#"<script type="module" src="http://host.docker.internal:5173/@vite/client">"#
+        ));
+        assert!(rendered_layout.contains(
+            r#"<script type="module" src="http://host.docker.internal:5173/src/main.ts">"#
+        ));
+    }
+
+    #[test]
+    fn test_development_main_integrity() {
+        let rendered_layout = (Development::default()
+            .main_integrity("sha384-devHash")
+            .into_config()
+            .layout())("{}".to_string());
+        assert!(rendered_layout.contains(
+            r#"<script type="module" src="http://localhost:5173/src/main.ts" integrity="sha384-devHash">"#
+        ));
+
+        let rendered_layout = (Development::default().into_config().layout())("{}".to_string());
+        assert!(!rendered_layout.contains("integrity"));
+    }
+
+    #[test]
+    fn test_development_production_guard() {
+        const ENV_VAR: &str = "AXUM_INERTIA_TEST_PRODUCTION_GUARD";
+        std::env::set_var(ENV_VAR, "production");
+        let build = |guard| {
+            std::panic::catch_unwind(|| {
+                Development::default()
+                    .env_var(ENV_VAR)
+                    .production_guard(guard)
+                    .into_config();
+            })
+        };
+
+        let strict = build(ProductionGuard::Panic);
+        let warn = build(ProductionGuard::Warn);
+        std::env::set_var(ENV_VAR, "development");
+        let development = build(ProductionGuard::Panic);
+        std::env::remove_var(ENV_VAR);
+
+        let message = strict.unwrap_err();
+        assert_eq!(
+            message.downcast_ref::<String>().unwrap(),
+            "AXUM_INERTIA_TEST_PRODUCTION_GUARD=production, but the Inertia config uses the vite dev server at http://localhost:5173"
+        );
+        assert!(warn.is_ok());
+        assert!(development.is_ok());
+    }
+
+    #[test]
+    fn test_development_add_main() {
+        let rendered_layout = (Development::default()
+            .add_main("src/worker.ts")
+            .into_config()
+            .layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(
+            r#"<script type="module" src="http://localhost:5173/@vite/client"></script><script type="module" src="http://localhost:5173/src/main.ts"></script><script type="module" src="http://localhost:5173/src/worker.ts"></script>"#
+        ));
+        assert_eq!(rendered_layout.matches("@vite/client").count(), 1);
+    }
+
+    #[test]
+    fn test_development_module_type() {
+        let rendered_layout = (Development::default()
+            .module_type(false)
+            .into_config()
+            .layout())("{}".to_string());
+        assert!(rendered_layout
+            .contains(r#"<script type="module" src="http://localhost:5173/@vite/client">"#));
+        assert!(rendered_layout.contains(r#"<script src="http://localhost:5173/src/main.ts">"#));
+
+        let rendered_layout = (Development::default().into_config().layout())("{}".to_string());
+        assert!(rendered_layout
+            .contains(r#"<script type="module" src="http://localhost:5173/src/main.ts">"#));
+    }
+
+    #[test]
+    fn test_production_asset_url_rewriter() {
+        let manifest_content = r#"{
+            "main.js": {"file": "main.hash-id-here.js", "css": ["style.css"]},
+            "font.woff2": {"file": "font.hash.woff2"}
+        }"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .preload_font("font.woff2")
+            .asset_url_rewriter(|url| format!("https://eu.cdn.example.com{url}"))
+            .into_config();
+
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(
+            r#"<script type="module" src="https://eu.cdn.example.com/main.hash-id-here.js">"#
+        ));
+        assert!(rendered_layout
+            .contains(r#"<link rel="stylesheet" href="https://eu.cdn.example.com/style.css">"#));
+        assert!(rendered_layout
+            .contains(r#"<link rel="preload" href="https://eu.cdn.example.com/font.hash.woff2""#));
+        assert!(!rendered_layout.contains(r#"="/"#));
+    }
+
+    #[test]
+    fn test_production_doctype() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let production = || Production::new_from_string(manifest_content, "main.js").unwrap();
+
+        let rendered_layout = (production().into_config().layout())("{}".to_string());
+        assert!(rendered_layout.starts_with(r#"<!DOCTYPE html><html lang="en">"#));
+
+        let rendered_layout = (production().doctype(None).into_config().layout())("{}".to_string());
+        assert!(rendered_layout.starts_with(r#"<html lang="en">"#));
+
+        let rendered_layout = (Development::default()
+            .doctype(Some(r#"<?xml version="1.0" encoding="UTF-8"?>"#))
+            .into_config()
+            .layout())("{}".to_string());
+        assert!(rendered_layout.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?><html"#));
+    }
+
+    #[test]
+    fn test_production_module_type() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .module_type(false)
+            .into_config();
+
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(r#"<script src="/main.hash-id-here.js"></script>"#));
+    }
+
+    #[test]
+    fn test_development_into_config() {
+        let main_script = "src/index.ts";
+        let development = Development::default()
+            .port(8080)
+            .main(main_script)
+            .lang("lang-id")
+            .title("app-title-here")
+            .react();
+
+        let config = development.into_config();
+
+        assert_eq!(config.version(), None);
+
+        let config_layout = config.layout();
+        let binding = config_layout(r#"{"someprops": "somevalues"}"#.to_string());
+        let rendered_layout = binding.as_str();
+
+        assert!(rendered_layout.contains(r#"<html lang="lang-id">"#));
+        assert!(rendered_layout.contains(r#"<title>app-title-here</title>"#));
+        assert!(rendered_layout.contains(r#"{&quot;someprops&quot;: &quot;somevalues&quot;}"#));
+        assert!(rendered_layout.contains(r#"http://localhost:8080/@vite/client"#));
+        assert!(
+            rendered_layout.contains(r#"window.__vite_plugin_react_preamble_installed__ = true"#)
+        );
+    }
+
+    #[test]
+    fn test_development_react_preamble_is_idempotent() {
+        let preamble = Development::default().react().build_react_preamble();
+
+        let (before, guarded) = preamble
+            .split_once("if (!window.__vite_plugin_react_preamble_installed__) {\n")
+            .expect("preamble is not guarded");
+        assert_eq!(
+            before.trim(),
+            r#"import RefreshRuntime from "http://localhost:5173/@react-refresh""#
+        );
+        let guarded = guarded.trim_end().strip_suffix('}').unwrap();
+        for assignment in [
+            "RefreshRuntime.injectIntoGlobalHook(window)",
+            "window.$RefreshReg$ = ",
+            "window.$RefreshSig$ = ",
+            "window.__vite_plugin_react_preamble_installed__ = true",
+        ] {
+            assert!(guarded.contains(assignment), "{assignment} is not guarded");
+        }
+    }
+
+    #[test]
+    fn test_development_csp() {
+        let config = Development::default().port(5174).csp().into_config();
+
+        assert_eq!(
+            config.html_headers().get(CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self'; script-src 'self' 'unsafe-inline' http://localhost:5174; \
+             style-src 'self' 'unsafe-inline' http://localhost:5174; \
+             img-src 'self' data: http://localhost:5174; \
+             font-src 'self' data: http://localhost:5174; \
+             connect-src 'self' http://localhost:5174 ws://localhost:5174"
+        );
+
+        let config = Development::default()
+            .csp_policy("default-src 'none'")
+            .into_config();
+        assert_eq!(
+            config.html_headers().get(CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'none'"
+        );
+
+        let config = Development::default().into_config();
+        assert!(config.html_headers().get(CONTENT_SECURITY_POLICY).is_none());
+    }
+
+    #[test]
+    fn test_development_minimal_head() {
+        let config = Development::default()
+            .minimal_head()
+            .head_html(r#"<title>Custom</title><meta charset="utf-8">"#)
+            .into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(r#"<title>Custom</title><meta charset="utf-8"></head>"#));
+        assert!(!rendered_layout.contains("<title>Inertia App</title>"));
+        assert!(!rendered_layout.contains("viewport"));
+
+        let config = Development::default().into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+        assert!(rendered_layout.contains("<title>Inertia App</title>"));
+        assert!(rendered_layout.contains("viewport"));
+    }
+
+    #[test]
+    fn test_production_minimal_head() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .minimal_head()
+            .head_html(r#"<link rel="icon" href="/favicon.svg">"#)
+            .into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(r#"<link rel="icon" href="/favicon.svg">"#));
+        assert!(!rendered_layout.contains("<title>"));
+        assert!(!rendered_layout.contains("charset"));
+        assert!(!rendered_layout.contains("viewport"));
+    }
+
+    #[test]
+    fn test_development_custom_react_preamble() {
+        let config = Development::default()
+            .port(5174)
+            .react_preamble(
+                "import RefreshRuntime from \"{dev_server_url}/@react-refresh\"\nwindow.__custom_preamble__ = true",
+            )
+            .into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(
+            r#"<script type="module">import RefreshRuntime from "http://localhost:5174/@react-refresh"
+window.__custom_preamble__ = true</script>"#
+        ));
+        assert!(!rendered_layout.contains("__vite_plugin_react_preamble_installed__"));
+    }
+
+    #[test]
+    fn test_development_client_query() {
+        let config = Development::default()
+            .client_query("hmr=1")
+            .client_query("?plugin=my plugin")
+            .into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(
+            r#"<script type="module" src="http://localhost:5173/@vite/client?hmr=1&amp;plugin=my%20plugin"></script>"#
+        ));
+    }
+
+    #[test]
+    fn test_development_hot_url() {
+        let development = Development::default().hot_url("https://app.test:3000\n");
+
+        assert_eq!(development.scheme, "https");
+        assert_eq!(development.host, "app.test");
+        assert_eq!(development.port, 3000);
+        assert_eq!(development.dev_server_url(), "https://app.test:3000");
+
+        let development = Development::default().hot_url("http://[::1]:5174/");
+
+        assert_eq!(development.host, "[::1]");
+        assert_eq!(development.port, 5174);
+    }
+
+    #[test]
+    fn test_development_from_missing_hot_file() {
+        let development = Development::from_hot_file("does/not/exist/hot");
+
+        assert_eq!(development.dev_server_url(), "http://localhost:5173");
+    }
+
+    #[test]
+    fn test_production_new_entry_missing() {
+        let manifest_content = r#"{"main.js": {}}"#;
+        let result = Production::new_from_string(manifest_content, "nonexistent.js");
+
+        assert!(matches!(result, Err(_)));
+    }
+
+    #[test]
+    fn test_production_new_empty_manifest() {
+        for manifest_content in ["", "  \n\t "] {
+            let result = Production::new_from_string(manifest_content, "main.js");
+
+            let err = result.err().unwrap();
+            assert_eq!(err.downcast_ref(), Some(&ViteError::EmptyManifest));
+        }
+    }
+
+    #[test]
+    fn test_production_new_manifest_missing() {
+        let result = Production::new("does/not/exist/manifest.json", "main.js");
+
+        let err = result.err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<ViteError>(),
+            Some(ViteError::ManifestMissing(_))
+        ));
+    }
+
+    #[test]
+    fn test_production_validate_integrity() {
+        let valid = format!("sha384-{}", BASE64_STANDARD.encode([7u8; 48]));
+        let manifest_content = format!(
+            r#"{{"main.js": {{"file": "main.js", "integrity": "{valid}", "imports": ["_chunk.js"]}},
+                "_chunk.js": {{"file": "chunk.js"}}}}"#
+        );
+        assert!(Production::new_from_string(&manifest_content, "main.js")
+            .unwrap()
+            .validate_integrity()
+            .is_ok());
+
+        for integrity in [
+            "sha1-AAAA",
+            "sha384-not base64!",
+            "sha256-AAAA",
+            "garbage",
+            "",
+            "  ",
+        ] {
+            let manifest_content = format!(
+                r#"{{"main.js": {{"file": "main.js", "integrity": "{valid}"}},
+                    "_chunk.js": {{"file": "chunk.js", "integrity": "{integrity}"}}}}"#
+            );
+            let err = Production::new_from_string(&manifest_content, "main.js")
+                .unwrap()
+                .validate_integrity()
+                .err()
+                .unwrap();
+            assert_eq!(
+                err.downcast_ref::<ViteError>(),
+                Some(&ViteError::InvalidIntegrity {
+                    entry: "_chunk.js".to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_production_from_bytes() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+
+        let from_bytes = Production::from_bytes(manifest_content.as_bytes(), "main.js").unwrap();
+        let from_reader =
+            Production::from_reader(std::io::Cursor::new(manifest_content), "main.js").unwrap();
+        let from_string = Production::new_from_string(manifest_content, "main.js").unwrap();
+
+        assert_eq!(from_bytes.main.file, "main.hash-id-here.js");
+        assert_eq!(from_reader.main.file, "main.hash-id-here.js");
+        assert_eq!(from_bytes.version, from_string.version);
+        assert_eq!(from_reader.version, from_string.version);
+    }
+
+    #[test]
+    fn test_production_entry_pattern() {
+        let manifest_content = r#"{
+            "src/main.ts": {"file": "assets/main.hash.js", "isEntry": true},
+            "src/admin/main.ts": {"file": "assets/admin.hash.js", "isEntry": true},
+            "src/other.ts": {"file": "assets/other.hash.js"}
+        }"#;
+
+        let production = Production::new_from_string(manifest_content, "*/admin/main.ts").unwrap();
+        assert_eq!(production.main.file, "assets/admin.hash.js");
+
+        let production = Production::new_from_string(manifest_content, "src/oth*").unwrap();
+        assert_eq!(production.main.file, "assets/other.hash.js");
+
+        let err = Production::new_from_string(manifest_content, "*/missing.ts")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<ViteError>(),
+            Some(&ViteError::EntryMissing("*/missing.ts"))
+        );
+
+        let err = Production::new_from_string(manifest_content, "*/main.ts")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<ViteError>(),
+            Some(&ViteError::AmbiguousEntry {
+                pattern: "*/main.ts",
+                matches: vec!["src/admin/main.ts".to_string(), "src/main.ts".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_vite_error_eq() {
+        let err = Production::new_from_string(r#"{"main.js": {"file": "main.js"}}"#, "other.js")
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            err.downcast_ref::<ViteError>(),
+            Some(&ViteError::EntryMissing("other.js"))
+        );
+        assert_ne!(
+            ViteError::EntryMissing("main.js"),
+            ViteError::EntryMissing("other.js")
+        );
+        assert_eq!(
+            ViteError::ManifestMissing(std::io::ErrorKind::NotFound.into()),
+            ViteError::ManifestMissing(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "manifest.json"
+            ))
+        );
+        assert_ne!(
+            ViteError::ManifestMissing(std::io::ErrorKind::NotFound.into()),
+            ViteError::EmptyManifest
+        );
+    }
+
+    #[test]
+    fn test_production_new() {
+        let manifest_content =
+            r#"{"main.js": {"file": "main.hash-id-here.js", "css": ["style.css"]}}"#;
+        let production_res = Production::new_from_string(manifest_content, "main.js");
+
+        assert!(production_res.is_ok());
+
+        let production = production_res.unwrap();
+        let content_hash = encode(Sha1::digest(manifest_content.as_bytes()));
+
+        assert_eq!(production.main.css, Some(vec!(String::from("style.css"))));
+        assert_eq!(production.title, "Inertia App");
+        assert_eq!(production.main.file, "main.hash-id-here.js");
+        assert_eq!(production.main.integrity, None);
+        assert_eq!(production.lang, "en");
+        assert_eq!(production.version, content_hash);
+    }
+
+    #[test]
+    #[cfg(feature = "json5")]
+    fn test_production_json5_manifest() {
+        let manifest_content = r#"{
+            // Hand-edited entry
+            "main.js": {"file": "main.hash-id-here.js", "css": ["style.css",],},
+        }"#;
+        let production = Production::new_from_string(manifest_content, "main.js").unwrap();
+
+        assert_eq!(production.main.file, "main.hash-id-here.js");
+        assert_eq!(
+            production.version,
+            encode(Sha1::digest(manifest_content.as_bytes()))
+        );
+    }
+
+    #[test]
+    fn test_production_entry_names() {
+        let manifest_content = r#"{
+            "src/main.ts": {"file": "main.js", "isEntry": true, "imports": ["_shared.js"]},
+            "src/admin.ts": {"file": "admin.js", "isEntry": true},
+            "_shared.js": {"file": "shared.js"}
+        }"#;
+        let production = Production::new_from_string(manifest_content, "src/main.ts").unwrap();
+        assert_eq!(
+            production.entry_names(),
+            vec!["src/admin.ts", "src/main.ts"]
+        );
+
+        let manifest_content = r#"{
+            "main.js": {"file": "main.hash-id-here.js"},
+            "admin.js": {"file": "admin.hash-id-here.js"}
+        }"#;
+        let production = Production::new_from_string(manifest_content, "main.js").unwrap();
+        assert_eq!(production.entry_names(), vec!["admin.js", "main.js"]);
+    }
+
+    #[tokio::test]
+    async fn test_production_debug_handler() {
+        let manifest_content =
+            r#"{"main.js": {"file": "main.hash-id-here.js", "css": ["style.css"]}}"#;
+        let production = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .asset_path("static");
 
-    #[test]
-    fn test_development_default() {
-        let development = Development::default();
+        let axum::Json(info) = (production.debug_handler())().await;
 
-        assert_eq!(development.port, 5173);
-        assert_eq!(development.main, "src/main.ts");
-        assert_eq!(development.lang, "en");
-        assert_eq!(development.title, "Vite");
-        assert_eq!(development.react, false);
+        assert_eq!(info["environment"], "production");
+        assert_eq!(info["version"], json!(production.version));
+        assert_eq!(info["main"], "/static/main.hash-id-here.js");
+        assert_eq!(info["css"], json!(["/static/style.css"]));
+        assert_eq!(info["entries"], json!(["main.js"]));
     }
 
     #[test]
-    fn test_development_builder_methods() {
-        let development = Development::default()
-            .port(8080)
-            .main("src/deep/index.ts")
-            .lang("id")
-            .title("Untitled Axum Inertia App")
-            .react();
+    fn test_production_public_root() {
+        let public =
+            std::env::temp_dir().join(format!("axum-inertia-public-{}", std::process::id()));
+        std::fs::create_dir_all(public.join("build/.vite")).unwrap();
+        let manifest_path = public.join("build/.vite/manifest.json");
+        let manifest_content =
+            r#"{"main.js": {"file": "assets/main.hash-id-here.js", "css": ["assets/style.css"]}}"#;
+        std::fs::write(&manifest_path, manifest_content).unwrap();
+        let manifest_path: &'static str = manifest_path.to_str().unwrap().to_owned().leak();
+
+        let production = Production::new(manifest_path, "main.js");
+        std::fs::remove_dir_all(&public).unwrap();
+        let production = production.unwrap();
+
+        // vite 5 writes `dist/.vite/manifest.json` with files relative
+        // to `dist`.
+        let rendered_layout = (production
+            .public_root(public.join("build"))
+            .try_into_config()
+            .unwrap()
+            .layout())("{}".to_string());
+        assert!(rendered_layout.contains(r#"src="/assets/main.hash-id-here.js""#));
+        assert!(rendered_layout.contains(r#"href="/assets/style.css""#));
 
-        assert_eq!(development.port, 8080);
-        assert_eq!(development.main, "src/deep/index.ts");
-        assert_eq!(development.lang, "id");
-        assert_eq!(development.title, "Untitled Axum Inertia App");
-        assert_eq!(development.react, true);
+        let production = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .loaded_from(manifest_path);
+        let rendered_layout = (production
+            .public_root(&public)
+            .asset_path("static")
+            .try_into_config()
+            .unwrap()
+            .layout())("{}".to_string());
+        assert!(rendered_layout.contains(r#"src="/static/build/assets/main.hash-id-here.js""#));
+        assert!(rendered_layout.contains(r#"href="/static/build/assets/style.css""#));
+
+        let production = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .loaded_from(manifest_path);
+        let result = production
+            .public_root(public.join("build/assets/js"))
+            .try_into_config();
+        assert_eq!(
+            result.err(),
+            Some(ViteError::OutsidePublicRoot(
+                "assets/main.hash-id-here.js".to_string()
+            ))
+        );
     }
 
     #[test]
-    fn test_development_into_config() {
-        let main_script = "src/index.ts";
-        let development = Development::default()
-            .port(8080)
-            .main(main_script)
-            .lang("lang-id")
-            .title("app-title-here")
-            .react();
+    fn test_production_is_stale() {
+        let manifest_path =
+            std::env::temp_dir().join(format!("axum-inertia-stale-{}.json", std::process::id()));
+        std::fs::write(&manifest_path, r#"{"main.js": {"file": "main.js"}}"#).unwrap();
+        let manifest_path: &'static str = manifest_path.to_str().unwrap().to_owned().leak();
+
+        let production = Production::new(manifest_path, "main.js");
+        std::fs::remove_file(manifest_path).unwrap();
+        let production = production.unwrap();
+
+        let an_hour = std::time::Duration::from_secs(3600);
+        assert!(production.is_stale(SystemTime::now() + an_hour));
+        assert!(!production.is_stale(SystemTime::now() - an_hour));
+
+        let production =
+            Production::new_from_string(r#"{"main.js": {"file": "main.js"}}"#, "main.js").unwrap();
+        assert!(!production.is_stale(SystemTime::now() + an_hour));
+    }
 
-        let config = development.into_config();
+    #[tokio::test]
+    #[cfg(feature = "tokio")]
+    async fn test_production_new_async() {
+        let manifest_path =
+            std::env::temp_dir().join(format!("axum-inertia-manifest-{}.json", std::process::id()));
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        std::fs::write(&manifest_path, manifest_content).unwrap();
+        let manifest_path: &'static str = manifest_path.to_str().unwrap().to_owned().leak();
 
-        assert_eq!(config.version(), None);
+        let production = Production::new_async(manifest_path, "main.js").await;
+        std::fs::remove_file(manifest_path).unwrap();
 
-        let config_layout = config.layout();
-        let binding = config_layout(r#"{"someprops": "somevalues"}"#.to_string());
-        let rendered_layout = binding.as_str();
+        let production = production.unwrap();
+        assert_eq!(production.main.file, "main.hash-id-here.js");
+        assert_eq!(
+            production.version,
+            encode(Sha1::digest(manifest_content.as_bytes()))
+        );
 
-        assert!(rendered_layout.contains(r#"<html lang="lang-id">"#));
-        assert!(rendered_layout.contains(r#"<title>app-title-here</title>"#));
-        assert!(rendered_layout.contains(r#"{&quot;someprops&quot;: &quot;somevalues&quot;}"#));
-        assert!(rendered_layout.contains(r#"http://localhost:8080/@vite/client"#));
-        assert!(
-            rendered_layout.contains(r#"window.__vite_plugin_react_preamble_installed__ = true"#)
+        let err = Production::new_async("does/not/exist.json", "main.js")
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<ViteError>(),
+            Some(&ViteError::ManifestMissing(
+                std::io::ErrorKind::NotFound.into()
+            ))
         );
     }
 
     #[test]
-    fn test_production_new_entry_missing() {
-        let manifest_content = r#"{"main.js": {}}"#;
-        let result = Production::new_from_string(manifest_content, "nonexistent.js");
+    fn test_production_strict_manifest() {
+        let manifest_content = r#"{
+            "src/main.ts": {"file": "main.js", "src": "src/main.ts", "isEntry": true, "dynamicImports": ["_admin.js"]},
+            "_admin.js": {"file": "admin.js", "renamedField": true}
+        }"#;
+
+        assert!(Production::from_manifest(manifest_content, "src/main.ts", false).is_ok());
+
+        let err = Production::from_manifest(manifest_content, "src/main.ts", true)
+            .err()
+            .unwrap();
+        let err = err.downcast_ref::<ViteError>().unwrap();
+        let ViteError::MalformedEntry { name, detail } = err else {
+            panic!("expected a malformed entry, got {err}");
+        };
+        assert_eq!(name, "_admin.js");
+        assert!(detail.starts_with("unknown field `renamedField`"));
+    }
 
-        assert!(matches!(result, Err(_)));
+    #[test]
+    fn test_production_malformed_entry() {
+        let manifest_content = r#"{
+            "main.js": {"file": "main.hash-id-here.js"},
+            "_shared.js": {"css": ["shared.css"]}
+        }"#;
+        let err = Production::new_from_string(manifest_content, "main.js")
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "malformed manifest entry _shared.js: missing field `file`"
+        );
     }
 
     #[test]
-    fn test_production_new() {
-        let manifest_content =
-            r#"{"main.js": {"file": "main.hash-id-here.js", "css": ["style.css"]}}"#;
-        let production_res = Production::new_from_string(manifest_content, "main.js");
+    fn test_production_version_from_sidecar() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let production = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .with_version("build-42\n");
 
-        assert!(production_res.is_ok());
+        assert_eq!(production.version, "build-42");
+        let config = production.into_config();
+        assert_eq!(config.version(), Some("build-42".to_string()));
+    }
 
-        let production = production_res.unwrap();
+    #[test]
+    fn test_production_version_falls_back_to_hash() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
         let content_hash = encode(Sha1::digest(manifest_content.as_bytes()));
 
-        assert_eq!(production.main.css, Some(vec!(String::from("style.css"))));
-        assert_eq!(production.title, "Vite");
-        assert_eq!(production.main.file, "main.hash-id-here.js");
-        assert_eq!(production.main.integrity, None);
-        assert_eq!(production.lang, "en");
+        let production = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .with_version_file("does/not/exist/version.txt");
+        assert_eq!(production.version, content_hash);
+
+        let production = production.with_version("  ");
         assert_eq!(production.version, content_hash);
     }
 
@@ -488,7 +2794,7 @@ mod tests {
 
         assert!(rendered_layout
             .contains(r#"<script type="module" src="/main.hash-id-here.js"></script>"#));
-        assert!(rendered_layout.contains(r#"<link rel="stylesheet" href="/style.css"/>"#));
+        assert!(rendered_layout.contains(r#"<link rel="stylesheet" href="/style.css">"#));
         assert!(rendered_layout.contains(r#"<html lang="jv">"#));
         assert!(rendered_layout.contains(r#"<title>Untitled Axum Inertia App</title>"#));
         assert!(rendered_layout.contains(r#"{&quot;someprops&quot;: &quot;somevalues&quot;}"#));
@@ -508,9 +2814,580 @@ mod tests {
         let rendered_layout = binding.as_str();
 
         assert!(rendered_layout.contains(r#"<script type="module" src="/main.hash-id-here.js" integrity="sha000-shaHashHere1234"></script>"#));
-        assert!(rendered_layout.contains(r#"<link rel="stylesheet" href="/style.css"/>"#));
+        assert!(rendered_layout.contains(r#"<link rel="stylesheet" href="/style.css">"#));
         assert!(rendered_layout.contains(r#"<html lang="jv">"#));
         assert!(rendered_layout.contains(r#"<title>Untitled Axum Inertia App</title>"#));
         assert!(rendered_layout.contains(r#"{&quot;someprops&quot;: &quot;somevalues&quot;}"#));
     }
+
+    #[test]
+    fn test_production_preload_font() {
+        let manifest_content = r#"{
+            "main.js": {"file": "main.hash-id-here.js"},
+            "fonts/inter.woff2": {"file": "assets/inter.hash-id-here.woff2"}
+        }"#;
+        let production = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .preload_font("fonts/inter.woff2")
+            .preload_font("https://fonts.example.com/mono.ttf");
+
+        let config = production.into_config();
+        let config_layout = config.layout();
+        let binding = config_layout(r#"{"someprops": "somevalues"}"#.to_string());
+        let rendered_layout = binding.as_str();
+
+        assert!(rendered_layout.contains(r#"<link rel="preload" href="/assets/inter.hash-id-here.woff2" as="font" type="font/woff2" crossorigin>"#));
+        assert!(rendered_layout.contains(r#"<link rel="preload" href="https://fonts.example.com/mono.ttf" as="font" type="font/ttf" crossorigin>"#));
+    }
+
+    #[test]
+    fn test_try_into_config_missing_application() {
+        let mut engine = Tera::default();
+        engine
+            .add_raw_template(
+                "layout.html",
+                "<html><head>{{ vite_main | safe }}</head><body></body></html>",
+            )
+            .unwrap();
+
+        let result = Development::default()
+            .template_engine(engine, "layout.html")
+            .try_into_config();
+
+        assert_eq!(
+            result.err(),
+            Some(ViteError::ApplicationMissing("layout.html".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_try_into_config_with_data_page_outside_of_the_mount_element() {
+        let mut engine = Tera::default();
+        engine
+            .add_raw_template(
+                "layout.html",
+                r#"<html><body><div id="{{ root_id }}"></div><script>{{ data_page | safe }}</script><div data-page='{{ data_page }}'></div></body></html>"#,
+            )
+            .unwrap();
+
+        let result = Development::default()
+            .template_engine(engine, "layout.html")
+            .try_into_config();
+
+        assert_eq!(
+            result.err(),
+            Some(ViteError::ApplicationMissing("layout.html".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_try_into_config_missing_template() {
+        let mut engine = Tera::default();
+        engine
+            .add_raw_template(
+                "layout.html",
+                "<html><body>{{ application | safe }}</body></html>",
+            )
+            .unwrap();
+
+        let result = Development::default()
+            .template_engine(engine.clone(), "")
+            .try_into_config();
+        assert_eq!(
+            result.err(),
+            Some(ViteError::TemplateMissing("".to_string()))
+        );
+
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let result = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .template_engine(engine, "layuot.html")
+            .try_into_config();
+        assert_eq!(
+            result.err(),
+            Some(ViteError::TemplateMissing("layuot.html".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_try_into_config_with_application() {
+        let mut engine = Tera::default();
+        engine
+            .add_raw_template(
+                "layout.html",
+                "<html><head>{{ vite_main | safe }}</head><body>{{ application | safe }}</body></html>",
+            )
+            .unwrap();
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+
+        let result = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .template_engine(engine, "layout.html")
+            .try_into_config();
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_production_link_header() {
+        let manifest_content =
+            r#"{"main.js": {"file": "main.hash-id-here.js", "css": ["style.css"]}}"#;
+        let production = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .asset_path("static")
+            .preload_font("https://fonts.example.com/inter.woff2")
+            .link_header();
+
+        let config = production.into_config();
+
+        assert_eq!(
+            config.html_headers().get(LINK).unwrap(),
+            "</static/main.hash-id-here.js>; rel=modulepreload, </static/style.css>; rel=preload; as=style, <https://fonts.example.com/inter.woff2>; rel=preload; as=font; crossorigin"
+        );
+    }
+
+    #[test]
+    fn test_production_csp() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .preload_font("https://fonts.example.com/inter.woff2")
+            .preload_font("https://fonts.example.com/inter-bold.woff2")
+            .csp()
+            .into_config();
+
+        assert_eq!(
+            config.html_headers().get(CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self'; script-src 'self'; style-src 'self'; \
+             img-src 'self' data:; font-src 'self' https://fonts.example.com; \
+             object-src 'none'; base-uri 'self'"
+        );
+
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .csp_policy("default-src 'self' https://cdn.example.com")
+            .into_config();
+        assert_eq!(
+            config.html_headers().get(CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self' https://cdn.example.com"
+        );
+    }
+
+    #[test]
+    fn test_production_csp_origins() {
+        let manifest_content =
+            r#"{"main.js": {"file": "main.hash-id-here.js", "css": ["style.css"]}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .preload_font("https://fonts.example.com/inter.woff2")
+            .preload_font("https://fonts.example.co/inter.woff2")
+            .asset_url_rewriter(|url| format!("https://cdn.example.com{url}"))
+            .csp()
+            .into_config();
+
+        assert_eq!(
+            config.html_headers().get(CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self'; script-src 'self' https://cdn.example.com; \
+             style-src 'self' https://cdn.example.com; img-src 'self' data:; \
+             font-src 'self' https://fonts.example.com https://fonts.example.co; \
+             object-src 'none'; base-uri 'self'"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid Content-Security-Policy")]
+    fn test_production_invalid_csp_policy() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let _ = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .csp_policy("default-src 'self'\nscript-src 'none'");
+    }
+
+    #[test]
+    fn test_production_crossorigin_and_referrer_policy() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js", "integrity": "sha000-shaHashHere1234", "css": ["style.css"]}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .preload_font("https://fonts.example.com/inter.woff2")
+            .crossorigin("anonymous")
+            .referrer_policy("no-referrer")
+            .link_header()
+            .into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(r#"<script type="module" src="/main.hash-id-here.js" integrity="sha000-shaHashHere1234" crossorigin="anonymous" referrerpolicy="no-referrer"></script>"#));
+        assert!(rendered_layout.contains(r#"<link rel="stylesheet" href="/style.css" crossorigin="anonymous" referrerpolicy="no-referrer">"#));
+        assert!(rendered_layout.contains(r#"<link rel="preload" href="https://fonts.example.com/inter.woff2" as="font" type="font/woff2" crossorigin="anonymous" referrerpolicy="no-referrer">"#));
+        assert_eq!(
+            config.html_headers().get(LINK).unwrap(),
+            "</main.hash-id-here.js>; rel=modulepreload; crossorigin; referrerpolicy=no-referrer, \
+             </style.css>; rel=preload; as=style; crossorigin; referrerpolicy=no-referrer, \
+             <https://fonts.example.com/inter.woff2>; rel=preload; as=font; crossorigin; referrerpolicy=no-referrer"
+        );
+    }
+
+    #[test]
+    fn test_production_inlines_small_css() {
+        let output_dir =
+            std::env::temp_dir().join(format!("axum-inertia-inline-css-{}", std::process::id()));
+        std::fs::create_dir_all(output_dir.join("assets")).unwrap();
+        std::fs::write(output_dir.join("assets/tiny.css"), "body{margin:0}").unwrap();
+        std::fs::write(output_dir.join("assets/large.css"), "a{}".repeat(1000)).unwrap();
+
+        let manifest_content = r#"{"main.js": {"file": "assets/main.js", "css": ["assets/tiny.css", "assets/large.css"]}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .output_dir(&output_dir)
+            .inline_css_below(1024)
+            .link_header()
+            .into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+        std::fs::remove_dir_all(&output_dir).unwrap();
+
+        assert!(rendered_layout.contains("<style>body{margin:0}</style>"));
+        assert!(!rendered_layout.contains("/assets/tiny.css"));
+        assert!(rendered_layout.contains(r#"<link rel="stylesheet" href="/assets/large.css">"#));
+        assert_eq!(
+            config.html_headers().get(LINK).unwrap(),
+            "</assets/main.js>; rel=modulepreload, </assets/large.css>; rel=preload; as=style"
+        );
+    }
+
+    #[test]
+    fn test_production_without_css() {
+        let manifest_content = r#"{
+            "main.js": {"file": "main.hash-id-here.js", "imports": ["_shared.js"]},
+            "_shared.js": {"file": "shared.hash-id-here.js"}
+        }"#;
+        let production = Production::new_from_string(manifest_content, "main.js").unwrap();
+
+        assert!(production.css_files().is_empty());
+
+        let rendered_layout = (production.into_config().layout())("{}".to_string());
+        assert!(!rendered_layout.contains("stylesheet"));
+    }
+
+    #[test]
+    fn test_production_css_only_from_imports() {
+        let manifest_content = r#"{
+            "main.js": {"file": "main.hash-id-here.js", "imports": ["_shared.js"]},
+            "_shared.js": {"file": "shared.hash-id-here.js", "css": ["shared.css"]}
+        }"#;
+        let production = Production::new_from_string(manifest_content, "main.js").unwrap();
+
+        assert_eq!(production.css_files(), vec!["shared.css"]);
+
+        let rendered_layout = (production.into_config().layout())("{}".to_string());
+        assert!(rendered_layout.contains(r#"<link rel="stylesheet" href="/shared.css">"#));
+    }
+
+    #[test]
+    fn test_production_css_from_imports_is_deduplicated() {
+        let manifest_content = r#"{
+            "main.js": {"file": "main.hash-id-here.js", "css": ["main.css", "common.css"], "imports": ["_admin.js", "_shared.js"]},
+            "_admin.js": {"file": "admin.hash-id-here.js", "css": ["admin.css", "common.css"], "imports": ["_shared.js"]},
+            "_shared.js": {"file": "shared.hash-id-here.js", "css": ["common.css", "shared.css"]}
+        }"#;
+        let production = Production::new_from_string(manifest_content, "main.js").unwrap();
+
+        assert_eq!(
+            production.css_files(),
+            vec!["main.css", "common.css", "shared.css", "admin.css"]
+        );
+
+        let config = production.into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert_eq!(rendered_layout.matches("/common.css").count(), 1);
+    }
+
+    #[test]
+    fn test_production_base_path() {
+        let manifest_content =
+            r#"{"main.js": {"file": "main.hash-id-here.js", "css": ["style.css"]}}"#;
+        let production = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .base_path("/app");
+
+        let config = production.into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout
+            .contains(r#"<script type="module" src="/app/main.hash-id-here.js"></script>"#));
+        assert!(rendered_layout.contains(r#"<link rel="stylesheet" href="/app/style.css">"#));
+        assert_eq!(config.url_for("/users"), "/app/users");
+    }
+
+    #[test]
+    fn test_production_from_entrypoints() {
+        let manifest_content = r#"{
+            "entrypoints": {
+                "app": {
+                    "js": ["/build/runtime.js", "/build/vendors.js", "/build/app.js"],
+                    "css": ["/build/app.css"]
+                },
+                "admin": {
+                    "js": ["https://cdn.example.com/vendors.js", "/build/admin.js"],
+                    "css": ["//cdn.example.com/theme.css"]
+                }
+            }
+        }"#;
+
+        let production = Production::from_format(Entrypoints, manifest_content, "app").unwrap();
+        let rendered_layout = (production.into_config().layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(concat!(
+            r#"<script type="module" src="/build/runtime.js"></script>"#,
+            r#"<script type="module" src="/build/vendors.js"></script>"#,
+            r#"<script type="module" src="/build/app.js"></script>"#
+        )));
+        assert!(rendered_layout.contains(r#"<link rel="stylesheet" href="/build/app.css">"#));
+
+        let production = Production::from_format(Entrypoints, manifest_content, "admin").unwrap();
+        let rendered_layout = (production.script_defer().into_config().layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(concat!(
+            r#"<script type="module" src="https://cdn.example.com/vendors.js"></script>"#,
+            r#"<script type="module" src="/build/admin.js" defer></script>"#
+        )));
+        assert!(rendered_layout
+            .contains(r#"<link rel="stylesheet" href="//cdn.example.com/theme.css">"#));
+
+        let err = Production::from_format(Entrypoints, manifest_content, "missing")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<ViteError>(),
+            Some(&ViteError::EntryMissing("missing"))
+        );
+    }
+
+    #[test]
+    fn test_production_script_loading() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let production = || Production::new_from_string(manifest_content, "main.js").unwrap();
+
+        let rendered_layout =
+            (production().script_defer().into_config().layout())("{}".to_string());
+        assert!(rendered_layout
+            .contains(r#"<script type="module" src="/main.hash-id-here.js" defer></script>"#));
+
+        let rendered_layout = (production()
+            .script_defer()
+            .script_async()
+            .into_config()
+            .layout())("{}".to_string());
+        assert!(rendered_layout
+            .contains(r#"<script type="module" src="/main.hash-id-here.js" async></script>"#));
+
+        let rendered_layout = (production().into_config().layout())("{}".to_string());
+        assert!(rendered_layout
+            .contains(r#"<script type="module" src="/main.hash-id-here.js"></script>"#));
+
+        // Only the main script is loaded asynchronously, the
+        // entrypoint's other scripts keep their order.
+        let manifest_content = r#"{"entrypoints": {"app": {"js": ["/build/runtime.js", "/build/vendors.js", "/build/app.js"]}}}"#;
+        let rendered_layout = (Production::from_format(Entrypoints, manifest_content, "app")
+            .unwrap()
+            .script_async()
+            .into_config()
+            .layout())("{}".to_string());
+        assert!(rendered_layout.contains(concat!(
+            r#"<script type="module" src="/build/runtime.js"></script>"#,
+            r#"<script type="module" src="/build/vendors.js"></script>"#,
+            r#"<script type="module" src="/build/app.js" async></script>"#
+        )));
+    }
+
+    #[test]
+    fn test_production_css_media() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js", "css": ["assets/main.css", "assets/print.css"]}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .css_media(|file| file.ends_with("print.css"), "print")
+            .into_config();
+
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(r#"<link rel="stylesheet" href="/assets/main.css">"#));
+        assert!(rendered_layout
+            .contains(r#"<link rel="stylesheet" href="/assets/print.css" media="print">"#));
+
+        // Attribute values are escaped like in the other tags.
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .css_media(|_| true, r#"print" onload="alert(1)"#)
+            .into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+        assert!(rendered_layout.contains(
+            r#"<link rel="stylesheet" href="/assets/main.css" media="print&quot; onload=&quot;alert(1)">"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_production_title_resolution() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let production = || Production::new_from_string(manifest_content, "main.js").unwrap();
+        let render = |config: InertiaConfig, props| async move {
+            let config = config.title_from_prop("title");
+            let request = http::Request::builder().uri("/").body(()).unwrap();
+            crate::testing::render_to_string(&config, request, "Home", props).await
+        };
+
+        let html = render(
+            production().title("My app").into_config(),
+            json!({ "title": "Home" }),
+        )
+        .await;
+        assert!(html.contains("<title>Home</title>"));
+
+        let html = render(production().title("My app").into_config(), json!({})).await;
+        assert!(html.contains("<title>My app</title>"));
+
+        let html = render(production().into_config(), json!({})).await;
+        assert!(html.contains("<title>Inertia App</title>"));
+    }
+
+    #[test]
+    fn test_production_build_info() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let production = || Production::new_from_string(manifest_content, "main.js").unwrap();
+
+        let rendered_layout = (production()
+            .build_info("4f2a9c1 2024-05-01T12:00:00Z")
+            .into_config()
+            .layout())("{}".to_string());
+        assert!(rendered_layout
+            .contains(r#"<meta name="build" content="4f2a9c1 2024-05-01T12:00:00Z">"#));
+
+        let rendered_layout = (production().into_config().layout())("{}".to_string());
+        assert!(!rendered_layout.contains(r#"name="build""#));
+    }
+
+    #[test]
+    fn test_production_base_tag() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let production = || Production::new_from_string(manifest_content, "main.js").unwrap();
+
+        let rendered_layout = (production()
+            .base_path("/app")
+            .base_tag()
+            .into_config()
+            .layout())("{}".to_string());
+        assert!(rendered_layout.contains(r#"<head><base href="/app/">"#));
+
+        let rendered_layout = (production().base_tag().into_config().layout())("{}".to_string());
+        assert!(rendered_layout.contains(r#"<base href="/">"#));
+
+        let rendered_layout =
+            (production().base_path("/app").into_config().layout())("{}".to_string());
+        assert!(!rendered_layout.contains("<base"));
+    }
+
+    fn broken_template_engine() -> Tera {
+        let mut engine = Tera::default();
+        engine
+            .add_raw_template(
+                "layout.html",
+                "<html><body>{{ application | safe }}{{ missing_variable }}</body></html>",
+            )
+            .unwrap();
+        engine
+    }
+
+    #[tokio::test]
+    async fn test_development_error_overlay() {
+        let config = Development::default()
+            .template_engine(broken_template_engine(), "layout.html")
+            .into_config();
+        let request = http::Request::builder().uri("/").body(()).unwrap();
+
+        let body = crate::testing::render_to_string(&config, request, "Home", json!({})).await;
+
+        assert!(body.contains("Failed to render Inertia response"));
+        assert!(body.contains("missing_variable"));
+    }
+
+    #[tokio::test]
+    async fn test_production_hides_render_errors() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .template_engine(broken_template_engine(), "layout.html")
+            .into_config();
+        let request = http::Request::builder().uri("/").body(()).unwrap();
+
+        let body = crate::testing::render_to_string(&config, request, "Home", json!({})).await;
+
+        assert_eq!(body, "Internal Server Error");
+    }
+
+    #[tokio::test]
+    async fn test_development_pretty_prints_the_page() {
+        let config = Development::default().into_config();
+        let request = http::Request::builder().uri("/").body(()).unwrap();
+
+        let body = crate::testing::render_to_string(&config, request, "Home", json!({})).await;
+
+        assert!(body.contains("data-page=\"{\n  &quot;component&quot;: &quot;Home&quot;,\n"));
+    }
+
+    #[tokio::test]
+    async fn test_production_compacts_the_page() {
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .into_config();
+        let request = http::Request::builder().uri("/").body(()).unwrap();
+
+        let body = crate::testing::render_to_string(&config, request, "Home", json!({})).await;
+
+        assert!(body.contains(r#"data-page="{&quot;component&quot;:&quot;Home&quot;,"#));
+    }
+
+    #[test]
+    fn test_production_template_context_prefix() {
+        let mut engine = Tera::default();
+        engine
+            .add_raw_template(
+                "layout.html",
+                r#"<html><body>{{ inertia.vite_main | safe }}{{ inertia.application | safe }}</body></html>"#,
+            )
+            .unwrap();
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .template_engine(engine, "layout.html")
+            .template_context_prefix("inertia")
+            .into_config();
+        let rendered_layout = (config.layout())("{}".to_string());
+
+        assert!(rendered_layout.contains(r#"<script type="module" src="/main.hash-id-here.js">"#));
+        assert!(rendered_layout.contains(r#"<div id="app" data-page="{}"></div>"#));
+    }
+
+    #[test]
+    fn test_production_template_with_raw_data_page() {
+        let mut engine = Tera::default();
+        engine
+            .add_raw_template(
+                "layout.html",
+                r#"<main class="shell"><section id="{{ root_id }}" data-page="{{ data_page }}"></section></main>"#,
+            )
+            .unwrap();
+        let manifest_content = r#"{"main.js": {"file": "main.hash-id-here.js"}}"#;
+        let config = Production::new_from_string(manifest_content, "main.js")
+            .unwrap()
+            .root_id("root")
+            .template_engine(engine, "layout.html")
+            .try_into_config()
+            .unwrap();
+
+        let rendered_layout = (config.layout())(r#"{"someprops": "somevalues"}"#.to_string());
+
+        assert_eq!(
+            rendered_layout,
+            r#"<main class="shell"><section id="root" data-page="{&quot;someprops&quot;: &quot;somevalues&quot;}"></section></main>"#
+        );
+    }
 }
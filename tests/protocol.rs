@@ -0,0 +1,142 @@
+//! End-to-end tests of the Inertia protocol against a real [Router],
+//! one test per protocol behavior.
+//!
+//! See https://inertiajs.com/the-protocol.
+use axum::body::{to_bytes, Body};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::Router;
+use axum_inertia::{Inertia, InertiaConfig, InertiaLayer};
+use http::{header, Request, StatusCode};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+fn app() -> Router {
+    let layout = Box::new(|props| {
+        format!(
+            r#"<html><body><div id="app" data-page='{}'></div></body></html>"#,
+            props
+        )
+    });
+    let config = InertiaConfig::new(Some("v1".to_string()), layout);
+
+    Router::new()
+        .route("/users", get(users))
+        .route("/users", post(create_user))
+        .layer(InertiaLayer::new(config))
+}
+
+async fn users(i: Inertia) -> impl IntoResponse {
+    i.render(
+        "Users/Index",
+        json!({ "teams": ["core"], "users": ["ada", "grace"] }),
+    )
+}
+
+async fn create_user(i: Inertia) -> impl IntoResponse {
+    i.redirect("/users")
+}
+
+async fn send(request: Request<Body>) -> (StatusCode, http::HeaderMap, String) {
+    let response = app().oneshot(request).await.unwrap();
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await.unwrap();
+    (
+        parts.status,
+        parts.headers,
+        String::from_utf8(body.to_vec()).unwrap(),
+    )
+}
+
+fn inertia_visit(uri: &str) -> http::request::Builder {
+    Request::builder()
+        .uri(uri)
+        .header("X-Inertia", "true")
+        .header("X-Inertia-Version", "v1")
+}
+
+#[tokio::test]
+async fn initial_page_load_renders_html() {
+    let request = Request::builder()
+        .uri("/users")
+        .body(Body::empty())
+        .unwrap();
+
+    let (status, headers, body) = send(request).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(headers.get("X-Inertia").is_none());
+    assert!(headers[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    assert_eq!(
+        body,
+        r#"<html><body><div id="app" data-page='{"component":"Users/Index","props":{"teams":["core"],"users":["ada","grace"]},"url":"/users","version":"v1"}'></div></body></html>"#
+    );
+}
+
+#[tokio::test]
+async fn inertia_visit_renders_json() {
+    let request = inertia_visit("/users").body(Body::empty()).unwrap();
+
+    let (status, headers, body) = send(request).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["X-Inertia"], "true");
+    assert_eq!(headers[header::VARY], "X-Inertia");
+    let page: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        page,
+        json!({
+            "component": "Users/Index",
+            "props": { "users": ["ada", "grace"], "teams": ["core"] },
+            "url": "/users",
+            "version": "v1",
+        })
+    );
+}
+
+#[tokio::test]
+async fn partial_reload_filters_props() {
+    let request = inertia_visit("/users")
+        .header("X-Inertia-Partial-Component", "Users/Index")
+        .header("X-Inertia-Partial-Data", "teams")
+        .body(Body::empty())
+        .unwrap();
+
+    let (status, _, body) = send(request).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let page: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(page["props"], json!({ "teams": ["core"] }));
+}
+
+#[tokio::test]
+async fn version_mismatch_forces_a_reload() {
+    let request = Request::builder()
+        .uri("/users?page=2")
+        .header("X-Inertia", "true")
+        .header("X-Inertia-Version", "v0")
+        .body(Body::empty())
+        .unwrap();
+
+    let (status, headers, body) = send(request).await;
+
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(headers["X-Inertia-Location"], "/users?page=2");
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn redirect_after_post_uses_see_other() {
+    let request = inertia_visit("/users")
+        .method("POST")
+        .body(Body::empty())
+        .unwrap();
+
+    let (status, headers, _) = send(request).await;
+
+    assert_eq!(status, StatusCode::SEE_OTHER);
+    assert_eq!(headers[header::LOCATION], "/users");
+}